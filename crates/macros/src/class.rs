@@ -0,0 +1,543 @@
+use crate::function::{js_args, JsArgs};
+use darling::{ast::NestedMeta, FromMeta};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    spanned::Spanned, Attribute, FnArg, ImplItem, ImplItemFn, Item, ItemImpl, Meta, PatType,
+    Signature, Type,
+};
+
+#[derive(Default, FromMeta)]
+#[darling(default)]
+struct ClassArgs {
+    /// the js class name, default to the rust type name
+    name: Option<String>,
+}
+
+#[derive(Default, FromMeta)]
+#[darling(default)]
+struct PropertyArgs {
+    /// the js property name, default to the rust method name
+    name: Option<String>,
+}
+
+pub(crate) fn expand_class(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let args = ClassArgs::from_list(&NestedMeta::parse_meta_list(attr)?)?;
+    let item: Item = syn::parse2(item)?;
+
+    let (ident, generics) = match &item {
+        Item::Struct(item) => (&item.ident, &item.generics),
+        Item::Enum(item) => (&item.ident, &item.generics),
+        _ => {
+            return Err(syn::Error::new(
+                item.span(),
+                "#[nodex::class] only supports structs and enums",
+            ))
+        }
+    };
+
+    if !generics.params.is_empty() {
+        return Err(syn::Error::new(
+            generics.span(),
+            "#[nodex::class] does not support generic types",
+        ));
+    }
+
+    let js_name = args
+        .name
+        .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+    let creator = format_ident!("__nodex_class_{}", ident);
+
+    Ok(quote! {
+        #item
+
+        impl ::nodex::value::NapiClass for #ident {
+            const NAME: &'static str = #js_name;
+        }
+
+        #[doc(hidden)]
+        #[allow(dead_code, non_snake_case)]
+        fn #creator(
+            env: ::nodex::env::NapiEnv,
+        ) -> ::nodex::NapiResult<::nodex::value::JsValue> {
+            use ::nodex::value::NapiValueT;
+            let class = <#ident as ::nodex::value::NapiClassMethods>::define(env)?;
+            Ok(class.value())
+        }
+
+        ::nodex::__napi_register! {
+            ::nodex::export::NapiExport::new(#js_name, #creator)
+        }
+    })
+}
+
+/// The role of a method in the js class.
+enum Kind {
+    Constructor,
+    Method(String),
+    Getter(String),
+    Setter(String),
+}
+
+/// Take the `#[constructor]`, `#[method]`, `#[getter]` and `#[setter]` attribute of a method.
+fn take_kind(method: &mut ImplItemFn) -> syn::Result<Kind> {
+    let mut kind = None;
+    let mut rest = vec![];
+
+    for attr in method.attrs.drain(..) {
+        let role = ["constructor", "method", "getter", "setter"]
+            .into_iter()
+            .find(|role| attr.path().is_ident(role));
+        match role {
+            Some(role) if kind.is_none() => kind = Some((role, property_args(&attr)?)),
+            Some(_) => return Err(syn::Error::new(
+                attr.span(),
+                "a method can only have one of #[constructor], #[method], #[getter] and #[setter]",
+            )),
+            None => rest.push(attr),
+        }
+    }
+    method.attrs = rest;
+
+    let ident = method.sig.ident.to_string();
+    let ident = ident.trim_start_matches("r#");
+    Ok(match kind {
+        Some(("constructor", _)) => Kind::Constructor,
+        Some(("getter", args)) => Kind::Getter(args.name.unwrap_or_else(|| ident.to_string())),
+        Some(("setter", args)) => Kind::Setter(
+            args.name
+                .unwrap_or_else(|| ident.strip_prefix("set_").unwrap_or(ident).to_string()),
+        ),
+        Some((_, args)) => Kind::Method(args.name.unwrap_or_else(|| ident.to_string())),
+        None => Kind::Method(ident.to_string()),
+    })
+}
+
+fn property_args(attr: &Attribute) -> syn::Result<PropertyArgs> {
+    match &attr.meta {
+        Meta::Path(_) => Ok(PropertyArgs::default()),
+        Meta::List(list) => Ok(PropertyArgs::from_list(&NestedMeta::parse_meta_list(
+            list.tokens.clone(),
+        )?)?),
+        Meta::NameValue(_) => Err(syn::Error::new(
+            attr.span(),
+            "expected #[role] or #[role(name = \"...\")]",
+        )),
+    }
+}
+
+/// `Self` or `NapiResult<Self>`, `Some(true)` if the instance is wrapped in a result.
+fn returns_self(ty: &Type, self_ty: &Type) -> Option<bool> {
+    let is_self = |ty: &Type| match ty {
+        Type::Path(path) => path.path.is_ident("Self") || ty == self_ty,
+        _ => false,
+    };
+
+    if is_self(ty) {
+        return Some(false);
+    }
+
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "NapiResult" && segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(ty) if is_self(ty) => Some(true),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The receiver of a method, `None` for static methods.
+fn receiver(sig: &Signature) -> syn::Result<Option<bool>> {
+    match sig.inputs.first() {
+        Some(FnArg::Receiver(receiver)) => {
+            if receiver.reference.is_none() || receiver.colon_token.is_some() {
+                Err(syn::Error::new(
+                    receiver.span(),
+                    "#[nodex::methods] only supports &self and &mut self receivers",
+                ))
+            } else {
+                Ok(Some(receiver.mutability.is_some()))
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+fn typed_inputs(sig: &Signature) -> Vec<&PatType> {
+    sig.inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(input) => Some(input),
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Borrow the native instance wrapped in `this` as `instance`, throw a TypeError if it is
+/// missing or `this` wraps another type, and an Error on a conflicting borrow. Returns the
+/// statements and the argument passed as the receiver.
+fn unwrap_this(
+    name: &TokenStream,
+    fallback: &TokenStream,
+    mutable: bool,
+) -> (TokenStream, TokenStream) {
+    let (borrow, receiver) = if mutable {
+        (
+            quote!(let mut instance = wrapped.borrow_mut()?;),
+            quote!(&mut *instance),
+        )
+    } else {
+        (
+            quote!(let instance = wrapped.borrow()?;),
+            quote!(&*instance),
+        )
+    };
+
+    let unwrap = quote! {
+        let wrapped = match ::nodex::value::Wrapped::<Self>::of(this) {
+            Ok(Some(wrapped)) => wrapped,
+            Err(::nodex::tag::TypeTagError::Napi(e)) => return Err(e),
+            Ok(None) | Err(::nodex::tag::TypeTagError::Mismatch { .. }) => {
+                env.throw_type_error(format!(
+                    "{}: `this` is not an instance of {}",
+                    #name,
+                    <Self as ::nodex::value::NapiClass>::NAME,
+                ))?;
+                #fallback
+            }
+        };
+        #borrow
+    };
+    (unwrap, receiver)
+}
+
+/// Convert the returned value to js, instances of `Self` are wrapped in a new js object.
+fn convert_ret(sig: &Signature, self_ty: &Type) -> TokenStream {
+    let output = match &sig.output {
+        syn::ReturnType::Type(_, ty) => returns_self(ty, self_ty),
+        syn::ReturnType::Default => None,
+    };
+
+    match output {
+        Some(false) => quote!(class.new_instance(ret).map(|value| value.value())),
+        Some(true) => quote!(class.new_instance(ret?).map(|value| value.value())),
+        None => quote!(::nodex::convert::ToJs::to_js(ret, env).map(|value| value.value())),
+    }
+}
+
+pub(crate) fn expand_methods(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    if !attr.is_empty() {
+        return Err(syn::Error::new(
+            attr.span(),
+            "#[nodex::methods] takes no arguments",
+        ));
+    }
+
+    let mut item: ItemImpl = syn::parse2(item)?;
+
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(syn::Error::new(
+            path.span(),
+            "#[nodex::methods] does not support trait impls",
+        ));
+    }
+
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            item.generics.span(),
+            "#[nodex::methods] does not support generic impls",
+        ));
+    }
+
+    let self_ty = item.self_ty.clone();
+    let undefined = quote!(return env.undefined().map(|value| value.value()););
+
+    let mut constructor = None;
+    let mut methods = vec![];
+    // (name, getter, setter), in declaration order
+    let mut accessors: Vec<(String, Option<TokenStream>, Option<TokenStream>)> = vec![];
+
+    for impl_item in item.items.iter_mut() {
+        let ImplItem::Fn(method) = impl_item else {
+            continue;
+        };
+
+        let kind = take_kind(method)?;
+        let sig = &method.sig;
+        let ident = &sig.ident;
+
+        if !sig.generics.params.is_empty() {
+            return Err(syn::Error::new(
+                sig.generics.span(),
+                "#[nodex::methods] does not support generic methods",
+            ));
+        }
+
+        if let Some(asyncness) = sig.asyncness {
+            return Err(syn::Error::new(
+                asyncness.span(),
+                "#[nodex::methods] does not support async methods",
+            ));
+        }
+
+        let receiver = receiver(sig)?;
+        let inputs = typed_inputs(sig);
+
+        match kind {
+            Kind::Constructor => {
+                if receiver.is_some() {
+                    return Err(syn::Error::new(
+                        sig.span(),
+                        "#[constructor] can not take self",
+                    ));
+                }
+
+                if constructor.is_some() {
+                    return Err(syn::Error::new(
+                        sig.span(),
+                        "only one #[constructor] is allowed",
+                    ));
+                }
+
+                let name = quote!(<Self as ::nodex::value::NapiClass>::NAME);
+                let JsArgs { extract, call, .. } = js_args(name, inputs, quote!(return Ok(this);))?;
+
+                let create = match &sig.output {
+                    syn::ReturnType::Type(_, ty) if returns_self(ty, &self_ty) == Some(true) => {
+                        quote!(Self::#ident(#(#call),*)?)
+                    }
+                    _ => quote!(Self::#ident(#(#call),*)),
+                };
+
+                constructor.replace(quote! {
+                    move |mut this, __args: ::nodex::args::JsArgs| {
+                        let env = this.env();
+                        let instance = match ::nodex::__private::take_native_instance::<Self>() {
+                            Some(instance) => instance,
+                            None => {
+                                #(#extract)*
+                                #create
+                            }
+                        };
+                        ::nodex::value::Wrapped::new(this, instance)?;
+                        Ok(this)
+                    }
+                });
+            }
+            Kind::Method(js_name) => {
+                let name = quote!(format!(
+                    "{}.{}",
+                    <Self as ::nodex::value::NapiClass>::NAME,
+                    #js_name,
+                ));
+                let JsArgs {
+                    mut call, extract, ..
+                } = js_args(name.clone(), inputs, undefined.clone())?;
+
+                let (instance, attribute) = match receiver {
+                    Some(mutable) => {
+                        let (instance, receiver) = unwrap_this(&name, &undefined, mutable);
+                        call.insert(0, receiver);
+                        (
+                            instance,
+                            quote!(::nodex::NapiPropertyAttributes::DefaultMethod),
+                        )
+                    }
+                    None => (
+                        quote!(),
+                        quote!(
+                            ::nodex::NapiPropertyAttributes::DefaultMethod
+                                | ::nodex::NapiPropertyAttributes::Static
+                        ),
+                    ),
+                };
+                let ret = convert_ret(sig, &self_ty);
+
+                methods.push(quote! {
+                    ::nodex::descriptor::DescriptorMethodBuilder::new()
+                        .with_utf8name(#js_name)
+                        .with_method({
+                            let class = class.clone();
+                            move |this, __args: ::nodex::args::JsArgs| {
+                                let env = this.env();
+                                #(#extract)*
+                                #instance
+                                let ret = Self::#ident(#(#call),*);
+                                #ret
+                            }
+                        })
+                        .with_attribute(#attribute)
+                        .build()?
+                });
+            }
+            Kind::Getter(js_name) => {
+                if receiver.is_none() {
+                    return Err(syn::Error::new(
+                        sig.span(),
+                        "#[getter] must take &self or &mut self",
+                    ));
+                }
+
+                if inputs
+                    .iter()
+                    .any(|input| !crate::function::is_env(&input.ty))
+                {
+                    return Err(syn::Error::new(
+                        sig.inputs.span(),
+                        "#[getter] can only take a NapiEnv besides self",
+                    ));
+                }
+
+                let name = quote!(format!(
+                    "{}.{}",
+                    <Self as ::nodex::value::NapiClass>::NAME,
+                    #js_name,
+                ));
+                let JsArgs { mut call, .. } = js_args(name.clone(), inputs, quote!())?;
+                let (instance, receiver) = unwrap_this(&name, &undefined, receiver == Some(true));
+                call.insert(0, receiver);
+                let ret = convert_ret(sig, &self_ty);
+
+                let getter = quote! {{
+                    let class = class.clone();
+                    move |this| {
+                        let env = this.env();
+                        #instance
+                        let ret = Self::#ident(#(#call),*);
+                        #ret
+                    }
+                }};
+
+                match accessors.iter_mut().find(|(name, _, _)| *name == js_name) {
+                    Some((_, Some(_), _)) => {
+                        return Err(syn::Error::new(
+                            sig.span(),
+                            format!("duplicated #[getter] for `{}`", js_name),
+                        ))
+                    }
+                    Some((_, slot, _)) => {
+                        slot.replace(getter);
+                    }
+                    None => accessors.push((js_name, Some(getter), None)),
+                }
+            }
+            Kind::Setter(js_name) => {
+                if receiver != Some(true) {
+                    return Err(syn::Error::new(sig.span(), "#[setter] must take &mut self"));
+                }
+
+                let name = quote!(format!(
+                    "{}.{}",
+                    <Self as ::nodex::value::NapiClass>::NAME,
+                    #js_name,
+                ));
+                let JsArgs {
+                    count,
+                    extract,
+                    mut call,
+                } = js_args(name.clone(), inputs, quote!(return Ok(());))?;
+
+                if count != 1 {
+                    return Err(syn::Error::new(
+                        sig.inputs.span(),
+                        "#[setter] must take exactly one value besides self",
+                    ));
+                }
+
+                let (instance, receiver) =
+                    unwrap_this(&name, &quote!(return Ok(());), receiver == Some(true));
+                call.insert(0, receiver);
+
+                let setter = quote! {
+                    move |this, value: ::nodex::value::JsValue| {
+                        let env = this.env();
+                        let __args = ::nodex::args::JsArgs(vec![value]);
+                        #(#extract)*
+                        #instance
+                        let ret = Self::#ident(#(#call),*);
+                        ::nodex::convert::ToJs::to_js(ret, env).map(|_| ())
+                    }
+                };
+
+                match accessors.iter_mut().find(|(name, _, _)| *name == js_name) {
+                    Some((_, _, Some(_))) => {
+                        return Err(syn::Error::new(
+                            sig.span(),
+                            format!("duplicated #[setter] for `{}`", js_name),
+                        ))
+                    }
+                    Some((_, _, slot)) => {
+                        slot.replace(setter);
+                    }
+                    None => accessors.push((js_name, None, Some(setter))),
+                }
+            }
+        }
+    }
+
+    let constructor = constructor.unwrap_or_else(|| {
+        quote! {
+            move |mut this, (): ()| {
+                let env = this.env();
+                match ::nodex::__private::take_native_instance::<Self>() {
+                    Some(instance) => {
+                        ::nodex::value::Wrapped::new(this, instance)?;
+                    }
+                    None => {
+                        env.throw_type_error(format!(
+                            "{} has no constructor",
+                            <Self as ::nodex::value::NapiClass>::NAME,
+                        ))?;
+                    }
+                }
+                Ok(this)
+            }
+        }
+    });
+
+    let accessors = accessors.into_iter().map(|(js_name, getter, setter)| {
+        let getter = getter.map(|getter| quote!(.with_getter(#getter)));
+        let setter = setter.map(|setter| quote!(.with_setter(#setter)));
+        quote! {
+            ::nodex::descriptor::DescriptorAccessorBuilder::<
+                ::nodex::value::JsValue,
+                ::nodex::value::JsValue,
+            >::new()
+                .with_utf8name(#js_name)
+                #getter
+                #setter
+                .with_attribute(::nodex::NapiPropertyAttributes::Configurable)
+                .build()?
+        }
+    });
+
+    Ok(quote! {
+        #item
+
+        impl ::nodex::value::NapiClassMethods for #self_ty {
+            #[allow(unused_variables, clippy::let_unit_value, clippy::unit_arg)]
+            fn define(
+                env: ::nodex::env::NapiEnv,
+            ) -> ::nodex::NapiResult<::nodex::value::JsClass> {
+                use ::nodex::value::NapiValueT;
+
+                let class = ::nodex::value::NapiClassRef::default();
+                let properties = vec![#(#methods,)* #(#accessors,)*];
+                let js_class = ::nodex::value::JsClass::new(
+                    env,
+                    <Self as ::nodex::value::NapiClass>::NAME,
+                    #constructor,
+                    properties,
+                )?;
+                class.set(js_class)?;
+
+                Ok(js_class)
+            }
+        }
+    })
+}
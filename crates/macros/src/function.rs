@@ -0,0 +1,249 @@
+use darling::{ast::NestedMeta, FromMeta};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{spanned::Spanned, FnArg, GenericArgument, ItemFn, PatType, PathArguments, Type};
+
+#[derive(Default, FromMeta)]
+#[darling(default)]
+struct FunctionArgs {
+    /// the property name on module exports, default to the rust function name
+    name: Option<String>,
+}
+
+/// `NapiEnv` parameters are passed in directly instead of being extracted from js arguments.
+pub(crate) fn is_env(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "NapiEnv")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Readable name of a rust type used in error messages.
+pub(crate) fn type_name(ty: &Type) -> String {
+    ty.to_token_stream().to_string().replace(' ', "")
+}
+
+/// The element type of a `Rest<T>` parameter, which takes the remaining js arguments.
+fn rest_of(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Rest" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(ty)) if args.args.len() == 1 => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The js arguments of a rust function.
+pub(crate) struct JsArgs {
+    /// the number of declared js arguments
+    pub count: usize,
+    /// statements extracting the rust arguments from `__args` by `FromJs`, after checking that
+    /// the required ones are passed
+    pub extract: Vec<TokenStream>,
+    /// the arguments passed to the rust function
+    pub call: Vec<TokenStream>,
+}
+
+/// Extract the rust arguments from the js values of `__args: JsArgs`, a TypeError prefixed by
+/// `name` is thrown if too few values are passed or on mismatch, and `fallback` is evaluated.
+///
+/// Trailing `Option<T>` arguments can be omitted, and a last `Rest<T>` argument takes the
+/// remaining values.
+pub(crate) fn js_args<'a>(
+    name: TokenStream,
+    inputs: impl IntoIterator<Item = &'a PatType>,
+    fallback: TokenStream,
+) -> syn::Result<JsArgs> {
+    let mut count = 0;
+    let mut optional = vec![];
+    let mut extract = vec![];
+    let mut call = vec![];
+    let mut rest = None;
+
+    for (idx, input) in inputs.into_iter().enumerate() {
+        let ty = &input.ty;
+        if is_env(ty) {
+            call.push(quote!(env));
+            continue;
+        }
+
+        if let Some(rest) = rest {
+            return Err(syn::Error::new(
+                rest,
+                "a Rest<T> argument must be the last js argument",
+            ));
+        }
+
+        let arg = format_ident!("__arg{}", idx);
+        let pat = input.pat.to_token_stream();
+        let index = count;
+
+        if let Some(element) = rest_of(ty) {
+            rest = Some(input.span());
+            let message = format!(
+                ": argument {{}} `{}` expected {}",
+                pat.to_string().replace('{', "{{").replace('}', "}}"),
+                type_name(element),
+            );
+            extract.push(quote! {
+                let mut #arg = ::std::vec::Vec::new();
+                for (index, value) in __args.0.iter().enumerate().skip(#index) {
+                    match ::nodex::convert::FromJs::from_js(*value) {
+                        Ok(value) => #arg.push(value),
+                        Err(_) => {
+                            env.throw_type_error(format!(concat!("{}", #message), #name, index))?;
+                            #fallback
+                        }
+                    }
+                }
+                let #arg: #ty = ::nodex::args::Rest(#arg);
+            });
+        } else {
+            let message = format!(": argument {} `{}` expected {}", index, pat, type_name(ty));
+            extract.push(quote! {
+                let #arg: #ty = match __args.0.get(#index) {
+                    Some(value) => match ::nodex::convert::FromJs::from_js(*value) {
+                        Ok(value) => value,
+                        Err(_) => {
+                            env.throw_type_error(format!("{}{}", #name, #message))?;
+                            #fallback
+                        }
+                    },
+                    // NB: only the optional arguments are omitted after the arity check
+                    None => <#ty as ::nodex::convert::FromJs>::omitted().unwrap(),
+                };
+            });
+        }
+
+        optional.push(quote!(<#ty as ::nodex::args::FromJsLastArg>::optional()));
+        call.push(quote!(#arg));
+        count += 1;
+    }
+
+    if count > 0 {
+        extract.insert(
+            0,
+            quote! {
+                let __min = [#(#optional),*]
+                    .iter()
+                    .rposition(|optional| !optional)
+                    .map_or(0, |index| index + 1);
+                if __args.0.len() < __min {
+                    env.throw_type_error(format!(
+                        "{}: expected at least {} argument{}, got {}",
+                        #name,
+                        __min,
+                        if __min == 1 { "" } else { "s" },
+                        __args.0.len(),
+                    ))?;
+                    #fallback
+                }
+            },
+        );
+    }
+
+    Ok(JsArgs {
+        count,
+        extract,
+        call,
+    })
+}
+
+pub(crate) fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let args = FunctionArgs::from_list(&NestedMeta::parse_meta_list(attr)?)?;
+    let func: ItemFn = syn::parse2(item)?;
+    let sig = &func.sig;
+
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            sig.generics.span(),
+            "#[nodex::function] does not support generic functions",
+        ));
+    }
+
+    let ident = &sig.ident;
+    let js_name = args
+        .name
+        .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+    let creator = format_ident!("__nodex_function_{}", ident);
+
+    let mut inputs = vec![];
+    for input in sig.inputs.iter() {
+        match input {
+            FnArg::Typed(input) if sig.asyncness.is_some() && is_env(&input.ty) => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "#[nodex::function] async functions can not take NapiEnv, it is not Send",
+                ))
+            }
+            FnArg::Typed(input) => inputs.push(input),
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new(
+                    receiver.span(),
+                    "#[nodex::function] does not support methods",
+                ))
+            }
+        }
+    }
+
+    let JsArgs {
+        extract,
+        call: call_args,
+        ..
+    } = js_args(
+        quote!(#js_name),
+        inputs,
+        quote!(return env.undefined().map(|value| value.value());),
+    )?;
+
+    // NB: the future of an async function is spawned on the runtime of the `rt` feature, and
+    // a promise is returned to js.
+    let ret = if sig.asyncness.is_some() {
+        quote!(::nodex::rt::spawn(env, #ident(#(#call_args),*)).map(|value| value.value()))
+    } else {
+        quote! {
+            let ret = #ident(#(#call_args),*);
+            ::nodex::convert::ToJs::to_js(ret, env).map(|value| value.value())
+        }
+    };
+
+    Ok(quote! {
+        #func
+
+        #[doc(hidden)]
+        #[allow(dead_code, non_snake_case, clippy::let_unit_value, clippy::unit_arg)]
+        fn #creator(
+            env: ::nodex::env::NapiEnv,
+        ) -> ::nodex::NapiResult<::nodex::value::JsValue> {
+            use ::nodex::value::NapiValueT;
+
+            let func = env.func_named(
+                #js_name,
+                move |this, __args: ::nodex::args::JsArgs| {
+                    let env = this.env();
+                    #(#extract)*
+                    #ret
+                },
+            )?;
+
+            Ok(func.value())
+        }
+
+        ::nodex::__napi_register! {
+            ::nodex::export::NapiExport::new(#js_name, #creator)
+        }
+    })
+}
@@ -1,6 +1,60 @@
 use proc_macro::TokenStream;
 use quote::quote;
 
+mod class;
+mod function;
+
+/// Export a rust function to js.
+///
+/// The js arguments are extracted by `FromJs` and the returned value is converted by `ToJs`,
+/// a `NapiEnv` parameter is passed in directly. The function is registered at link time and
+/// set on the module exports by `napi_module!`.
+///
+/// An `async fn` returns a promise to js, its future is spawned on the runtime of the `rt`
+/// feature and the output settles the promise. The arguments of an `async fn` must be `Send`.
+///
+/// * `name` - the exported name, default to the rust function name
+#[proc_macro_attribute]
+pub fn function(attr: TokenStream, item: TokenStream) -> TokenStream {
+    function::expand(attr.into(), item.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Export a rust struct or enum as a js class, the methods are defined by
+/// `#[nodex::methods]`. The class is registered at link time and set on the module exports by
+/// `napi_module!`.
+///
+/// * `name` - the class name, default to the rust type name
+#[proc_macro_attribute]
+pub fn class(attr: TokenStream, item: TokenStream) -> TokenStream {
+    class::expand_class(attr.into(), item.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Define the js side of a `#[nodex::class]` from an impl block.
+///
+/// Every method in the block is exposed to js, with the role given by an attribute:
+///
+/// * `#[constructor]` - called by `new`, returns `Self` or `NapiResult<Self>`, which is then
+///   wrapped in `this`
+/// * `#[method]` or none - a method on the prototype if it takes `&self` or `&mut self`,
+///   otherwise a static method on the class
+/// * `#[getter]` - the getter of a property, takes `&self` or `&mut self`
+/// * `#[setter]` - the setter of a property, takes `&mut self` and the value, the property name
+///   defaults to the method name without `set_`
+///
+/// Each of them accepts `name = "..."` to rename the js property. Arguments and returned values
+/// are converted like `#[nodex::function]`, except that returned `Self` instances are wrapped in
+/// new js objects of the class.
+#[proc_macro_attribute]
+pub fn methods(attr: TokenStream, item: TokenStream) -> TokenStream {
+    class::expand_methods(attr.into(), item.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
 #[proc_macro]
 pub fn init(input: TokenStream) -> TokenStream {
     init_impl(input.into())
@@ -19,15 +73,11 @@ fn init_impl(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2::TokenS
             let env = nodex_api::env::NapiEnv::from_raw(env);
             let exports = nodex_api::value::JsValue::from_raw(env, exports);
 
-            // TODO: deal with exception
-            match std::panic::catch_unwind(|| #input(env, exports)) {
-                Ok(r) => {
-                }
-                Err(e) => {
-                }
-            }
-
-            exports.raw()
+            // NB: an error or a panic is thrown, then `require` throws it
+            nodex_api::callback::invoke(env, move || {
+                #input(env, exports)?;
+                Ok(exports.raw())
+            })
         }
     })
 }
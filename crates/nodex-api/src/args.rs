@@ -1,9 +1,34 @@
 use crate::{api, env::NapiEnv, prelude::*};
+use std::collections::HashMap;
 
 /// Js args
+///
+/// As `FromJsArgs`, it takes all the args passed by the js caller, e.g. for a variadic function.
 #[derive(Debug, Clone)]
 pub struct JsArgs(pub Vec<JsValue>);
 
+/// The remaining args of a call, which must be the last element of a tuple of args.
+///
+/// ```
+/// # use nodex_api::prelude::*;
+/// fn join(env: NapiEnv) -> NapiResult<()> {
+///     env.func(|_, (separator, words): (String, Rest<String>)| Ok(words.0.join(&separator)))?;
+///     Ok(())
+/// }
+/// ```
+///
+/// A `Rest<T>` which is not the last element is a compile error:
+///
+/// ```compile_fail
+/// # use nodex_api::prelude::*;
+/// fn join(env: NapiEnv) -> NapiResult<()> {
+///     env.func(|_, (words, separator): (Rest<String>, String)| Ok(words.0.join(&separator)))?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rest<T>(pub Vec<T>);
+
 /// Trait for types convertible to any number of Js values.
 pub trait ToJsArgs {
     fn to_js_args(self, env: NapiEnv) -> NapiResult<JsArgs>;
@@ -11,9 +36,117 @@ pub trait ToJsArgs {
 }
 
 /// Trait for types that can be created from an arbitrary number of Js values.
+///
+/// The args are the ones passed by the js caller, which may be fewer than `len()`, trailing
+/// args of type `Option<T>` or `Rest<T>` can be omitted. A `Rest<T>` can only be the last
+/// element of a tuple.
 pub trait FromJsArgs: Sized {
     fn from_js_args(args: JsArgs) -> NapiResult<Self>;
+
+    /// The number of declared args.
     fn len() -> usize;
+
+    /// The number of args which can not be omitted.
+    fn min_len() -> usize {
+        Self::len()
+    }
+}
+
+/// An element of a tuple of args, which is converted from the args at `index`.
+pub trait FromJsArg: Sized {
+    fn from_js_arg(args: &[JsValue], index: usize) -> NapiResult<Self>;
+
+    /// The arg can be omitted by the js caller.
+    fn optional() -> bool;
+}
+
+impl<T: FromJs> FromJsArg for T {
+    fn from_js_arg(args: &[JsValue], index: usize) -> NapiResult<T> {
+        match args.get(index) {
+            Some(arg) => T::from_js(*arg).map_err(|e| mismatch::<T>(*arg, index, e)),
+            None => T::omitted().ok_or(NapiStatus::InvalidArg),
+        }
+    }
+
+    fn optional() -> bool {
+        T::omitted().is_some()
+    }
+}
+
+/// The last element of a tuple of args, which may be a `Rest<T>` as well.
+pub trait FromJsLastArg: Sized {
+    fn from_js_last_arg(args: &[JsValue], index: usize) -> NapiResult<Self>;
+
+    /// The arg can be omitted by the js caller.
+    fn optional() -> bool;
+}
+
+impl<T: FromJsArg> FromJsLastArg for T {
+    fn from_js_last_arg(args: &[JsValue], index: usize) -> NapiResult<T> {
+        T::from_js_arg(args, index)
+    }
+
+    fn optional() -> bool {
+        <T as FromJsArg>::optional()
+    }
+}
+
+impl<T: FromJs> FromJsLastArg for Rest<T> {
+    fn from_js_last_arg(args: &[JsValue], index: usize) -> NapiResult<Rest<T>> {
+        let rest = args.get(index..).unwrap_or_default();
+        rest.iter()
+            .enumerate()
+            .map(|(i, arg)| T::from_js(*arg).map_err(|e| mismatch::<T>(*arg, index + i, e)))
+            .collect::<NapiResult<Vec<_>>>()
+            .map(Rest)
+    }
+
+    fn optional() -> bool {
+        true
+    }
+}
+
+impl<T: FromJs> FromJsArgs for Rest<T> {
+    fn from_js_args(args: JsArgs) -> NapiResult<Rest<T>> {
+        Rest::from_js_last_arg(&args.0, 0)
+    }
+
+    fn len() -> usize {
+        0
+    }
+}
+
+impl FromJsArgs for JsArgs {
+    fn from_js_args(args: JsArgs) -> NapiResult<JsArgs> {
+        Ok(args)
+    }
+
+    fn len() -> usize {
+        0
+    }
+}
+
+/// Convert the args passed by a js caller, a TypeError is thrown if too few args are passed.
+pub(crate) fn from_call<T: FromJsArgs>(env: NapiEnv, args: JsArgs) -> NapiResult<T> {
+    let min = T::min_len();
+    if args.0.len() < min {
+        return Err(too_few(env, min, args.0.len()));
+    }
+    T::from_js_args(args)
+}
+
+/// Throw a TypeError for a call which passes `argc` args, fewer than `min`.
+pub(crate) fn too_few(env: NapiEnv, min: usize, argc: usize) -> NapiStatus {
+    let message = format!(
+        "expected at least {} argument{}, got {}",
+        min,
+        if min == 1 { "" } else { "s" },
+        argc,
+    );
+    match env.throw_type_error(message) {
+        Ok(()) => NapiStatus::PendingException,
+        Err(e) => e,
+    }
 }
 
 impl FromJsArgs for () {
@@ -36,16 +169,50 @@ impl ToJsArgs for () {
     }
 }
 
+/// Throw a TypeError for the argument at `index` which is not a `T`, unless an exception is
+/// already pending.
+pub(crate) fn mismatch<T>(arg: JsValue, index: usize, status: NapiStatus) -> NapiStatus {
+    expected::<T>(arg, format!("argument {}", index), status)
+}
+
+/// Throw a TypeError for `what` which is not a `T`, unless an exception is already pending.
+pub(crate) fn expected<T>(value: JsValue, what: String, status: NapiStatus) -> NapiStatus {
+    let env = value.env();
+    if status == NapiStatus::PendingException || env.is_exception_pending().unwrap_or(true) {
+        return NapiStatus::PendingException;
+    }
+    let message = format!("{} expected {}", what, type_name::<T>());
+    match env.throw_type_error(message) {
+        Ok(()) => NapiStatus::PendingException,
+        Err(e) => e,
+    }
+}
+
+/// Readable name of a rust type, without the module paths.
+pub(crate) fn type_name<T>() -> String {
+    let name = std::any::type_name::<T>();
+    let mut readable = String::with_capacity(name.len());
+    let mut segment = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            readable.push_str(&segment);
+            segment.clear();
+            readable.push(c);
+        }
+    }
+    readable.push_str(&segment);
+    readable
+}
+
 impl<T: NapiValueT> FromJsArgs for T {
     fn from_js_args(args: JsArgs) -> NapiResult<T> {
-        // It's safe here
-        let arg = unsafe { args.0.get_unchecked(0) };
-        let casted = unsafe { arg.cast::<T>() };
-        match casted.check() {
-            Ok(true) => Ok(casted),
-            Ok(false) => Err(NapiStatus::InvalidArg),
-            Err(e) => Err(e),
-        }
+        T::from_js_arg(&args.0, 0)
     }
 
     fn len() -> usize {
@@ -53,6 +220,17 @@ impl<T: NapiValueT> FromJsArgs for T {
     }
 }
 
+/// Js args are passed as they are, the number of them is only known at runtime.
+impl ToJsArgs for JsArgs {
+    fn to_js_args(self, _: NapiEnv) -> NapiResult<JsArgs> {
+        Ok(self)
+    }
+
+    fn len() -> usize {
+        0
+    }
+}
+
 impl<T: NapiValueT> ToJsArgs for T {
     fn to_js_args(self, env: NapiEnv) -> NapiResult<JsArgs> {
         Ok(JsArgs(vec![self.value()]))
@@ -63,9 +241,65 @@ impl<T: NapiValueT> ToJsArgs for T {
     }
 }
 
-macro_rules! count {
-    () => (0_usize);
-    ($x:tt $($xs:tt)*) => (1_usize + count!($($xs)*));
+/// Rust values are passed as a single js argument.
+macro_rules! js_args_single {
+    ($({$($G:ident),*} $T:ty),+ $(,)?) => {
+        $(
+            impl<$($G: FromJs),*> FromJsArgs for $T {
+                fn from_js_args(args: JsArgs) -> NapiResult<Self> {
+                    <$T>::from_js_arg(&args.0, 0)
+                }
+
+                fn len() -> usize {
+                    1
+                }
+
+                fn min_len() -> usize {
+                    (!<$T as FromJsArg>::optional()) as usize
+                }
+            }
+
+            impl<$($G: ToJs),*> ToJsArgs for $T {
+                fn to_js_args(self, env: NapiEnv) -> NapiResult<JsArgs> {
+                    Ok(JsArgs(vec![self.to_js(env)?.value()]))
+                }
+
+                fn len() -> usize {
+                    1
+                }
+            }
+        )+
+    };
+}
+
+js_args_single!(
+    {} bool,
+    {} i8,
+    {} i16,
+    {} i32,
+    {} i64,
+    {} isize,
+    {} u8,
+    {} u16,
+    {} u32,
+    {} u64,
+    {} usize,
+    {} f32,
+    {} f64,
+    {} String,
+    {T} Option<T>,
+    {T} Vec<T>,
+    {T} HashMap<String, T>,
+);
+
+impl ToJsArgs for &str {
+    fn to_js_args(self, env: NapiEnv) -> NapiResult<JsArgs> {
+        Ok(JsArgs(vec![self.to_js(env)?.value()]))
+    }
+
+    fn len() -> usize {
+        1
+    }
 }
 
 #[macro_export]
@@ -73,33 +307,37 @@ macro_rules! from_js_args_tuple {
     () => ();
 
     ($($name:ident),+; $($idx:tt),+) => (
+        $crate::from_js_args_tuple!(@last []; $($name),+; $($idx),+);
+    );
+
+    // NB: only the last element may be a `Rest<T>`
+    (@last [$($init:ident $at:tt)*]; $name:ident, $($names:ident),+; $idx:tt, $($idxs:tt),+) => (
+        $crate::from_js_args_tuple!(@last [$($init $at)* $name $idx]; $($names),+; $($idxs),+);
+    );
+
+    (@last [$($name:ident $idx:tt)*]; $last:ident; $last_idx:tt) => (
         #[doc(hidden)]
-        impl<$($name: NapiValueT + NapiValueCheck),*> FromJsArgs for ($($name,)*) {
+        impl<$($name: FromJsArg,)* $last: FromJsLastArg> FromJsArgs for ($($name,)* $last,) {
             fn from_js_args(args: JsArgs) -> NapiResult<Self> {
-                Ok(($({
-                    // It's safe here
-                    let arg = unsafe { args.0.get_unchecked($idx) };
-                    let casted = unsafe { arg.cast::<$name>() };
-
-                    match casted.check() {
-                        Ok(true) => {
-                            casted
-                        }
-                        Ok(false) => {
-                            return Err(NapiStatus::InvalidArg)
-                        }
-                        Err(e) => {
-                            return Err(e)
-                        }
-                    }
-                },)*))
+                Ok((
+                    $($name::from_js_arg(&args.0, $idx)?,)*
+                    $last::from_js_last_arg(&args.0, $last_idx)?,
+                ))
             }
 
             fn len() -> usize {
-                count!($($name)*)
+                count!($($name)* $last)
+            }
+
+            fn min_len() -> usize {
+                // NB: the args after the last required one can be omitted
+                [$(<$name as FromJsArg>::optional(),)* <$last as FromJsLastArg>::optional()]
+                    .iter()
+                    .rposition(|optional| !optional)
+                    .map_or(0, |index| index + 1)
             }
         }
-    )
+    );
 }
 
 from_js_args_tuple!(T0; 0);
@@ -109,7 +347,7 @@ from_js_args_tuple!(T0, T1, T2, T3; 0, 1, 2, 3);
 from_js_args_tuple!(T0, T1, T2, T3, T4; 0, 1, 2, 3, 4);
 from_js_args_tuple!(T0, T1, T2, T3, T4, T5; 0, 1, 2, 3, 4, 5);
 from_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6; 0, 1, 2, 3, 4, 5, 6);
-from_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7; 0, 1, 2, 3, 4, 5, 6, 6);
+from_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7; 0, 1, 2, 3, 4, 5, 6, 7);
 from_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8; 0, 1, 2, 3, 4, 5, 6, 7, 8);
 from_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9);
 from_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10);
@@ -125,13 +363,12 @@ macro_rules! to_js_args_tuple {
 
     ($($name:ident),+; $($idx:tt),+) => (
         #[doc(hidden)]
-        impl<$($name: NapiValueT + NapiValueCheck),+> ToJsArgs for ($($name,)+) {
+        impl<$($name: ToJs),+> ToJsArgs for ($($name,)+) {
             fn to_js_args(self, env: NapiEnv) -> NapiResult<JsArgs> {
                 let mut args = vec![];
-                let mut idx = 0;
 
                 $({
-                    args.push(self.$idx.value());
+                    args.push(self.$idx.to_js(env)?.value());
                 })+
 
                 Ok(JsArgs(args))
@@ -151,7 +388,7 @@ to_js_args_tuple!(T0, T1, T2, T3; 0, 1, 2, 3);
 to_js_args_tuple!(T0, T1, T2, T3, T4; 0, 1, 2, 3, 4);
 to_js_args_tuple!(T0, T1, T2, T3, T4, T5; 0, 1, 2, 3, 4, 5);
 to_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6; 0, 1, 2, 3, 4, 5, 6);
-to_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7; 0, 1, 2, 3, 4, 5, 6, 6);
+to_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7; 0, 1, 2, 3, 4, 5, 6, 7);
 to_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8; 0, 1, 2, 3, 4, 5, 6, 7, 8);
 to_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9);
 to_js_args_tuple!(T0, T1, T2, T3, T4, T5, T6, T7, T8, T9, T10; 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10);
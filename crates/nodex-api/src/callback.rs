@@ -1,4 +1,13 @@
 use crate::{api, prelude::*};
+use std::{
+    any::Any,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The number of args `CallbackInfo::get` expects by default, which covers most of the calls
+/// without fetching the args twice.
+pub(crate) const ARGS_CAPACITY: usize = 8;
 
 #[derive(Copy, Clone, Debug)]
 pub struct CallbackInfo(NapiEnv, napi_callback_info);
@@ -26,4 +35,296 @@ impl CallbackInfo {
             Ok(Some(JsObject::from_raw(self.env(), value)))
         }
     }
+
+    /// The number of args passed by the js caller.
+    pub fn argc(&self) -> NapiResult<usize> {
+        let mut argc = 0;
+        napi_call!(
+            napi_get_cb_info,
+            self.env(),
+            self.raw(),
+            &mut argc,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+        Ok(argc)
+    }
+
+    /// `this`, the args passed by the js caller and the data of the callback, `capacity` is the
+    /// expected number of args, the args are fetched again if more are passed.
+    pub(crate) fn get(&self, capacity: usize) -> NapiResult<(JsObject, JsArgs, DataPointer)> {
+        let mut this = std::ptr::null_mut();
+        let mut data = std::ptr::null_mut();
+        let mut argc = capacity;
+        let mut argv = vec![std::ptr::null_mut(); capacity];
+        napi_call!(
+            napi_get_cb_info,
+            self.env(),
+            self.raw(),
+            &mut argc,
+            argv.as_mut_ptr(),
+            &mut this,
+            &mut data,
+        );
+
+        if argc > capacity {
+            argv.resize(argc, std::ptr::null_mut());
+            napi_call!(
+                napi_get_cb_info,
+                self.env(),
+                self.raw(),
+                &mut argc,
+                argv.as_mut_ptr(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+        }
+        // NB: napi fills the missing args with undefined, only the passed ones are kept
+        argv.truncate(argc);
+
+        let args = argv
+            .into_iter()
+            .map(|arg| JsValue::from_raw(self.env(), arg))
+            .collect();
+        Ok((JsObject::from_raw(self.env(), this), JsArgs(args), data))
+    }
+}
+
+/// The context of a js call into a rust closure, created by `NapiEnv::func_with_context` and
+/// `NapiEnv::class_with_context`.
+///
+/// Unlike `NapiEnv::func`, the args are not converted up front, so a closure can take any
+/// number of args, and a constructor can tell `new Foo()` apart from `Foo()`:
+///
+/// ```ignore
+/// let class = env.class_with_context("Point", (), |ctx| {
+///     if ctx.new_target()?.is_none() {
+///         return Err(NapiStatus::ObjectExpected);
+///     }
+///     let mut this = ctx.this::<JsObject>()?;
+///     this.set("x", env.double(ctx.arg::<f64>(0)?)?)?;
+///     this.set("y", env.double(ctx.arg::<Option<f64>>(1)?.unwrap_or(0.))?)?;
+///     Ok(this)
+/// }, [])?;
+/// ```
+pub struct CallContext<'a, D = ()> {
+    info: CallbackInfo,
+    this: JsObject,
+    args: JsArgs,
+    data: &'a D,
+}
+
+impl<'a, D> CallContext<'a, D> {
+    pub(crate) fn new(info: CallbackInfo, this: JsObject, args: JsArgs, data: &'a D) -> Self {
+        CallContext {
+            info,
+            this,
+            args,
+            data,
+        }
+    }
+
+    /// `NapiEnv` of the call.
+    pub fn env(&self) -> NapiEnv {
+        self.info.env()
+    }
+
+    /// The raw `CallbackInfo` of the call.
+    pub fn info(&self) -> CallbackInfo {
+        self.info
+    }
+
+    /// `this` of the call, a TypeError is thrown if it is not a `T`.
+    pub fn this<T: NapiValueT>(&self) -> NapiResult<T> {
+        self.this
+            .value()
+            .cast_checked::<T>()
+            .map_err(|e| crate::args::expected::<T>(self.this.value(), "`this`".into(), e))
+    }
+
+    /// `new.target` of a constructor call, None if the function is not called by `new`.
+    pub fn new_target(&self) -> NapiResult<Option<JsObject>> {
+        self.info.get_new_target()
+    }
+
+    /// The number of args passed by the js caller.
+    pub fn argc(&self) -> usize {
+        self.args.0.len()
+    }
+
+    /// Convert the arg at `index`, a TypeError is thrown on mismatch, or if the arg is omitted
+    /// and `T` is not an `Option<T>`.
+    pub fn arg<T: FromJs>(&self, index: usize) -> NapiResult<T> {
+        match self.args.0.get(index) {
+            Some(arg) => T::from_js(*arg).map_err(|e| crate::args::mismatch::<T>(*arg, index, e)),
+            None => {
+                T::omitted().ok_or_else(|| crate::args::too_few(self.env(), index + 1, self.argc()))
+            }
+        }
+    }
+
+    /// Convert all the args, like the args of a closure passed to `NapiEnv::func`.
+    pub fn args<T: FromJsArgs>(&self) -> NapiResult<T> {
+        crate::args::from_call(self.env(), self.args.clone())
+    }
+
+    /// The args passed by the js caller.
+    pub fn args_iter(&self) -> impl ExactSizeIterator<Item = JsValue> + '_ {
+        self.args.0.iter().copied()
+    }
+
+    /// The data passed when the function or class is created.
+    pub fn data(&self) -> &D {
+        self.data
+    }
+}
+
+/// Call into rust from a napi_callback, use it to implement a raw callback passed to
+/// NapiEnv::function_named() and the like.
+///
+/// * a panic is caught and thrown as an Error, instead of unwinding across the ffi boundary
+/// * an error is thrown as a js exception, a TypeError for the *_expected statuses
+/// * nothing is thrown if an exception is already pending
+/// * in debug builds, using a js value of another env in the callback panics
+pub fn invoke(env: NapiEnv, callback: impl FnOnce() -> NapiResult<napi_value>) -> napi_value {
+    #[cfg(debug_assertions)]
+    let _entered = Entered::new(env);
+
+    let result = match catch_unwind(AssertUnwindSafe(callback)) {
+        Ok(result) => result,
+        Err(panic) => {
+            let message = format!("panic: {}", panic_message(&panic));
+            throw(env, || env.throw_error(&message));
+            return std::ptr::null_mut();
+        }
+    };
+
+    match result {
+        Ok(value) => value,
+        Err(status) => {
+            throw(env, || match status {
+                NapiStatus::InvalidArg
+                | NapiStatus::ObjectExpected
+                | NapiStatus::StringExpected
+                | NapiStatus::NameExpected
+                | NapiStatus::FunctionExpected
+                | NapiStatus::NumberExpected
+                | NapiStatus::BooleanExpected
+                | NapiStatus::ArrayExpected
+                | NapiStatus::BigintExpected
+                | NapiStatus::DateExpected
+                | NapiStatus::ArraybufferExpected
+                | NapiStatus::DetachableArraybufferExpected => {
+                    env.throw_type_error(format!("{}", status))
+                }
+                _ => env.throw_error(format!("{}", status)),
+            });
+            // NB: the callback returns undefined to js
+            std::ptr::null_mut()
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    /// the env of the innermost callback running on this thread
+    static CURRENT: std::cell::Cell<Option<NapiEnv>> = const { std::cell::Cell::new(None) };
+}
+
+/// The env of a callback running on this thread, the outer env is restored when it is dropped.
+#[cfg(debug_assertions)]
+struct Entered(Option<NapiEnv>);
+
+#[cfg(debug_assertions)]
+impl Entered {
+    fn new(env: NapiEnv) -> Entered {
+        Entered(CURRENT.with(|current| current.replace(Some(env))))
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for Entered {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.0));
+    }
+}
+
+/// Panic if a js value of `env` is used in a callback of another env, e.g. a value kept in a
+/// global by the main thread is used by a worker thread.
+#[cfg(debug_assertions)]
+pub(crate) fn check_env(env: NapiEnv) {
+    if let Some(current) = CURRENT.with(|current| current.get()) {
+        if current.raw() != env.raw() {
+            panic!(
+                "nodex: a js value of env {:?} is used in env {:?}, js values can not be shared between envs",
+                env.raw(),
+                current.raw(),
+            );
+        }
+    }
+}
+
+/// Throw a js Error, the pending exception is reported by `NapiStatus::PendingException`.
+pub(crate) fn throw_error(env: NapiEnv, message: String) -> NapiStatus {
+    match env.throw_error(message) {
+        Ok(()) => NapiStatus::PendingException,
+        Err(e) => e,
+    }
+}
+
+/// Throw an exception unless one is pending.
+fn throw(env: NapiEnv, throw: impl FnOnce() -> NapiResult<()>) {
+    if !env.is_exception_pending().unwrap_or(true) {
+        if let Err(e) = throw() {
+            log::error!("nodex::callback::invoke: failed to throw: {}", e);
+        }
+    }
+}
+
+/// Run a native callback which has no js caller, like finalizers and hooks. Errors and panics
+/// can not be thrown there, so they are logged.
+pub(crate) fn guard(name: &str, callback: impl FnOnce() -> NapiResult<()>) {
+    match catch_unwind(AssertUnwindSafe(callback)) {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::error!("{}: {}", name, e),
+        Err(panic) => log::error!("{}: panic: {}", name, panic_message(&panic)),
+    }
+}
+
+/// The message of a caught panic.
+pub(crate) fn panic_message(panic: &Box<dyn Any + Send>) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+static LIVE_CLOSURES: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of rust closures handed to js which are not freed yet, i.e. the callbacks of
+/// functions, classes, property descriptors and threadsafe functions, which are freed when the
+/// js values owning them are garbage-collected. Useful to check an addon for leaks.
+pub fn live_closures() -> usize {
+    LIVE_CLOSURES.load(Ordering::SeqCst)
+}
+
+/// Counted by `live_closures` until dropped, kept together with the closure data.
+#[derive(Debug)]
+pub(crate) struct Live(());
+
+impl Live {
+    pub(crate) fn new() -> Live {
+        LIVE_CLOSURES.fetch_add(1, Ordering::SeqCst);
+        Live(())
+    }
+}
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        LIVE_CLOSURES.fetch_sub(1, Ordering::SeqCst);
+    }
 }
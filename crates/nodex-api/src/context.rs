@@ -69,7 +69,7 @@ impl NapiAsyncContext {
             self.raw(),
             this.raw(),
             func.raw(),
-            T::len(),
+            args.len(),
             args.as_ptr(),
         );
 
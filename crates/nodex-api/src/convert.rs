@@ -0,0 +1,332 @@
+use crate::{api, prelude::*};
+use std::{collections::HashMap, fmt::Display};
+
+/// Trait for rust values which can be converted into a js value.
+pub trait ToJs {
+    /// The js value this rust value is converted to.
+    type Value: NapiValueT;
+
+    fn to_js(self, env: NapiEnv) -> NapiResult<Self::Value>;
+}
+
+/// Trait for rust values which can be extracted from a js value.
+pub trait FromJs: Sized {
+    fn from_js(value: JsValue) -> NapiResult<Self>;
+
+    /// The value of a trailing argument omitted by the js caller, None if the argument is
+    /// required.
+    fn omitted() -> Option<Self> {
+        None
+    }
+}
+
+impl<T: NapiValueT> ToJs for T {
+    type Value = T;
+
+    #[inline]
+    fn to_js(self, _: NapiEnv) -> NapiResult<T> {
+        Ok(self)
+    }
+}
+
+impl<T: NapiValueT> FromJs for T {
+    #[inline]
+    fn from_js(value: JsValue) -> NapiResult<T> {
+        value.cast_checked::<T>()
+    }
+}
+
+impl ToJs for () {
+    type Value = JsUndefined;
+
+    #[inline]
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsUndefined> {
+        env.undefined()
+    }
+}
+
+/// Any js value is accepted and ignored.
+impl FromJs for () {
+    #[inline]
+    fn from_js(_: JsValue) -> NapiResult<()> {
+        Ok(())
+    }
+}
+
+/// `Err` is thrown as a js Error, unless there is already a pending exception.
+impl<T: ToJs, E: Display> ToJs for Result<T, E> {
+    type Value = T::Value;
+
+    fn to_js(self, env: NapiEnv) -> NapiResult<T::Value> {
+        match self {
+            Ok(value) => value.to_js(env),
+            Err(err) => {
+                if !env.is_exception_pending()? {
+                    env.throw_error(err.to_string())?;
+                }
+                Err(NapiStatus::PendingException)
+            }
+        }
+    }
+}
+
+/// `None` is converted to null.
+impl<T: ToJs> ToJs for Option<T> {
+    type Value = JsValue;
+
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsValue> {
+        match self {
+            Some(value) => Ok(value.to_js(env)?.value()),
+            None => Ok(env.null()?.value()),
+        }
+    }
+}
+
+/// Both null and undefined are extracted as `None`.
+impl<T: FromJs> FromJs for Option<T> {
+    fn from_js(value: JsValue) -> NapiResult<Option<T>> {
+        match value.kind()? {
+            NapiValuetype::Null | NapiValuetype::Undefined => Ok(None),
+            _ => Ok(Some(T::from_js(value)?)),
+        }
+    }
+
+    fn omitted() -> Option<Option<T>> {
+        Some(None)
+    }
+}
+
+impl<T: ToJs> ToJs for Vec<T> {
+    type Value = JsArray;
+
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsArray> {
+        let mut array = JsArray::new(env, self.len())?;
+        for (index, value) in self.into_iter().enumerate() {
+            array.set(index as u32, value.to_js(env)?)?;
+        }
+        Ok(array)
+    }
+}
+
+impl<T: FromJs> FromJs for Vec<T> {
+    fn from_js(value: JsValue) -> NapiResult<Vec<T>> {
+        let array = value.as_array()?;
+        (0..array.len()?)
+            .map(|index| T::from_js(array.get(index)?))
+            .collect()
+    }
+}
+
+impl<T: ToJs> ToJs for HashMap<String, T> {
+    type Value = JsObject;
+
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsObject> {
+        let mut object = env.object()?;
+        for (key, value) in self {
+            object.set(key, value.to_js(env)?)?;
+        }
+        Ok(object)
+    }
+}
+
+/// Only the own enumerable string keys of the object are extracted, like `Object.keys()`.
+///
+/// NB: before napi 6, the enumerable string keys of the prototype chain are extracted as well,
+/// like `for...in`.
+impl<T: FromJs> FromJs for HashMap<String, T> {
+    fn from_js(value: JsValue) -> NapiResult<HashMap<String, T>> {
+        let object = value.as_object()?;
+        #[cfg(feature = "v6")]
+        let keys = object.get_all_property_names(
+            NapiKeyCollectionMode::KeyOwnOnly,
+            NapiKeyFilter::Enumerable | NapiKeyFilter::SkipSymbols,
+            NapiKeyConversion::NumbersToStrings,
+        )?;
+        #[cfg(not(feature = "v6"))]
+        let keys = object.get_property_names()?;
+        (0..keys.len()?)
+            .map(|index| {
+                let key = keys.get(index)?;
+                let value = object.get_property(key)?;
+                Ok((String::from_js(key)?, T::from_js(value)?))
+            })
+            .collect()
+    }
+}
+
+impl ToJs for bool {
+    type Value = JsBoolean;
+
+    #[inline]
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsBoolean> {
+        env.boolean(self)
+    }
+}
+
+impl FromJs for bool {
+    #[inline]
+    fn from_js(value: JsValue) -> NapiResult<bool> {
+        value.as_boolean()?.get()
+    }
+}
+
+impl ToJs for String {
+    type Value = JsString;
+
+    #[inline]
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsString> {
+        env.string(self)
+    }
+}
+
+impl ToJs for &str {
+    type Value = JsString;
+
+    #[inline]
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsString> {
+        env.string(self)
+    }
+}
+
+impl FromJs for String {
+    #[inline]
+    fn from_js(value: JsValue) -> NapiResult<String> {
+        value.as_string()?.get()
+    }
+}
+
+impl ToJs for f64 {
+    type Value = JsNumber;
+
+    #[inline]
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsNumber> {
+        env.double(self)
+    }
+}
+
+impl FromJs for f64 {
+    #[inline]
+    fn from_js(value: JsValue) -> NapiResult<f64> {
+        value.as_number()?.get_value_double()
+    }
+}
+
+impl ToJs for f32 {
+    type Value = JsNumber;
+
+    #[inline]
+    fn to_js(self, env: NapiEnv) -> NapiResult<JsNumber> {
+        env.double(self as f64)
+    }
+}
+
+impl FromJs for f32 {
+    #[inline]
+    fn from_js(value: JsValue) -> NapiResult<f32> {
+        Ok(f64::from_js(value)? as f32)
+    }
+}
+
+/// The largest integer a js number can represent exactly, `Number.MAX_SAFE_INTEGER`.
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.;
+
+/// Throw a RangeError for an integer which a js number can not represent exactly.
+fn unsafe_integer(env: NapiEnv, value: impl std::fmt::Display, negative: bool) -> NapiStatus {
+    let (limit, name) = if negative {
+        (-MAX_SAFE_INTEGER, "Number.MIN_SAFE_INTEGER")
+    } else {
+        (MAX_SAFE_INTEGER, "Number.MAX_SAFE_INTEGER")
+    };
+    let message = format!(
+        "{} is beyond {} ({}), it can not be converted to a js number exactly",
+        value, name, limit,
+    );
+    match env.throw_range_error(message, None::<&str>) {
+        Ok(()) => NapiStatus::PendingException,
+        Err(e) => e,
+    }
+}
+
+/// Integers are extracted from js numbers only if the number is integral and fits in the
+/// target type, otherwise `NapiStatus::InvalidArg` is returned. Likewise, an integer beyond
+/// `Number.MAX_SAFE_INTEGER` is not converted to a js number, which would round it, a
+/// RangeError is thrown instead.
+macro_rules! int_to_from_js {
+    ($($T:ty => $create:ident),+ $(,)?) => {
+        $(
+            impl ToJs for $T {
+                type Value = JsNumber;
+
+                #[inline]
+                fn to_js(self, env: NapiEnv) -> NapiResult<JsNumber> {
+                    let number = self as f64;
+                    if number.abs() > MAX_SAFE_INTEGER {
+                        return Err(unsafe_integer(env, self, number < 0.));
+                    }
+                    env.$create(self as _)
+                }
+            }
+
+            impl FromJs for $T {
+                fn from_js(value: JsValue) -> NapiResult<$T> {
+                    let number = f64::from_js(value)?;
+                    if number.fract() != 0.
+                        || number.abs() > MAX_SAFE_INTEGER
+                        || number < <$T>::MIN as f64
+                        || number > <$T>::MAX as f64
+                    {
+                        return Err(NapiStatus::InvalidArg);
+                    }
+                    Ok(number as $T)
+                }
+            }
+        )+
+    };
+}
+
+int_to_from_js!(
+    i8 => int32,
+    i16 => int32,
+    i32 => int32,
+    i64 => int64,
+    isize => int64,
+    u8 => uint32,
+    u16 => uint32,
+    u32 => uint32,
+    u64 => double,
+    usize => double,
+);
+
+/// Tuples are converted from and to js arrays, an array of another length is refused.
+macro_rules! tuple_to_from_js {
+    ($($T:ident),+; $($idx:tt),+) => {
+        impl<$($T: ToJs),+> ToJs for ($($T,)+) {
+            type Value = JsArray;
+
+            fn to_js(self, env: NapiEnv) -> NapiResult<JsArray> {
+                let mut array = JsArray::new(env, count!($($T)+))?;
+                $(array.set($idx, self.$idx.to_js(env)?)?;)+
+                Ok(array)
+            }
+        }
+
+        impl<$($T: FromJs),+> FromJs for ($($T,)+) {
+            fn from_js(value: JsValue) -> NapiResult<Self> {
+                let array = value.as_array()?;
+                if array.len()? != count!($($T)+) as u32 {
+                    return Err(NapiStatus::InvalidArg);
+                }
+                Ok(($($T::from_js(array.get($idx)?)?,)+))
+            }
+        }
+    };
+}
+
+tuple_to_from_js!(T0; 0);
+tuple_to_from_js!(T0, T1; 0, 1);
+tuple_to_from_js!(T0, T1, T2; 0, 1, 2);
+tuple_to_from_js!(T0, T1, T2, T3; 0, 1, 2, 3);
+tuple_to_from_js!(T0, T1, T2, T3, T4; 0, 1, 2, 3, 4);
+tuple_to_from_js!(T0, T1, T2, T3, T4, T5; 0, 1, 2, 3, 4, 5);
+tuple_to_from_js!(T0, T1, T2, T3, T4, T5, T6; 0, 1, 2, 3, 4, 5, 6);
+tuple_to_from_js!(T0, T1, T2, T3, T4, T5, T6, T7; 0, 1, 2, 3, 4, 5, 6, 7);
@@ -1,26 +1,155 @@
-use crate::{api, prelude::*};
-use std::mem::MaybeUninit;
-
+use crate::{api, callback::Live, prelude::*};
+use std::{ffi::CString, mem::MaybeUninit, rc::Rc};
+
+/// A property descriptor, which owns the name and the closures the raw descriptor points to.
+///
+/// They are freed when the descriptor is dropped, unless it is defined by `define_properties`
+/// or `JsClass::new`:
+///
+/// * a method is defined as a js function, which frees the closure once it is garbage-collected
+/// * the closures of an accessor are freed once the object it is defined on is garbage-collected
 #[derive(Clone, Debug)]
-#[repr(C)]
-pub struct NapiPropertyDescriptor(napi_property_descriptor);
+pub struct NapiPropertyDescriptor {
+    raw: napi_property_descriptor,
+    owned: Rc<Owned>,
+}
 
 impl AsRef<napi_property_descriptor> for NapiPropertyDescriptor {
     fn as_ref(&self) -> &napi_property_descriptor {
-        &self.0
+        &self.raw
     }
 }
 
 impl std::ops::Deref for NapiPropertyDescriptor {
     type Target = napi_property_descriptor;
     fn deref(&self) -> &napi_property_descriptor {
-        &self.0
+        &self.raw
     }
 }
 
 impl NapiPropertyDescriptor {
+    /// The raw descriptor, which is only valid while this descriptor is alive.
     pub fn raw(&self) -> &napi_property_descriptor {
-        &self.0
+        &self.raw
+    }
+
+    /// The property is a method or an accessor implemented by rust closures.
+    pub(crate) fn has_closures(&self) -> bool {
+        self.owned.data.is_some()
+    }
+
+    /// The property is defined on the class instead of its prototype.
+    pub(crate) fn is_static(&self) -> bool {
+        NapiPropertyAttributes::from_bits_retain(self.raw.attributes)
+            .contains(NapiPropertyAttributes::Static)
+    }
+}
+
+/// The name and the closure data of a descriptor.
+#[derive(Debug, Default)]
+struct Owned {
+    utf8name: Option<CString>,
+    data: Option<Data>,
+}
+
+#[derive(Debug)]
+struct Data {
+    pointer: DataPointer,
+    drop: unsafe fn(DataPointer),
+    _live: Live,
+}
+
+impl Data {
+    fn new<T>(data: T) -> Data {
+        unsafe fn drop_box<T>(pointer: DataPointer) {
+            drop(Box::from_raw(pointer as *mut T));
+        }
+
+        Data {
+            pointer: Box::into_raw(Box::new(data)) as DataPointer,
+            drop: drop_box::<T>,
+            _live: Live::new(),
+        }
+    }
+}
+
+impl Drop for Data {
+    fn drop(&mut self) {
+        unsafe { (self.drop)(self.pointer) }
+    }
+}
+
+/// Raw descriptors ready to be defined, see `NapiPropertyDescriptor`.
+pub(crate) struct Prepared {
+    raw: Vec<napi_property_descriptor>,
+    // NB: the data of accessors, kept alive by the target object
+    accessors: Vec<Rc<Owned>>,
+}
+
+impl Prepared {
+    pub(crate) fn new(env: NapiEnv, properties: &[NapiPropertyDescriptor]) -> NapiResult<Prepared> {
+        let mut prepared = Prepared {
+            raw: Vec::with_capacity(properties.len()),
+            accessors: vec![],
+        };
+
+        for property in properties {
+            let mut raw = property.raw;
+            if property.owned.data.is_none() {
+                prepared.raw.push(raw);
+                continue;
+            }
+
+            if raw.method.is_some() {
+                // NB: the method is created as a function by hand, so its closure lives as
+                // long as the function, which may outlive the object it is defined on.
+                let len = if raw.utf8name.is_null() {
+                    0
+                } else {
+                    // NB: NAPI_AUTO_LENGTH
+                    usize::MAX
+                };
+                let value = napi_call!(
+                    =napi_create_function,
+                    env,
+                    raw.utf8name,
+                    len,
+                    raw.method,
+                    raw.data,
+                );
+                let owned = property.owned.clone();
+                JsFunction::from_raw(env, value).gc(move |_| {
+                    drop(owned);
+                    Ok(())
+                })?;
+
+                raw.method = None;
+                raw.data = std::ptr::null_mut();
+                raw.value = value;
+            } else {
+                prepared.accessors.push(property.owned.clone());
+            }
+            prepared.raw.push(raw);
+        }
+
+        Ok(prepared)
+    }
+
+    pub(crate) fn raw(&self) -> &[napi_property_descriptor] {
+        &self.raw
+    }
+
+    /// Free the accessors once `target` is garbage-collected.
+    pub(crate) fn keep_alive(self, target: impl NapiValueT) -> NapiResult<()> {
+        if self.accessors.is_empty() {
+            return Ok(());
+        }
+        let accessors = self.accessors;
+        target.value().gc(move |_| {
+            drop(accessors);
+            Ok(())
+        })?;
+        Ok(())
     }
 }
 
@@ -33,7 +162,6 @@ pub struct DescriptorValueBuilder {
 }
 
 /// The DescriptorBuild for method.
-/// NB: there seems no way to reclaim the napi_property_descriptor.data, so it is leaked.
 #[allow(clippy::type_complexity)]
 pub struct DescriptorMethodBuilder<T: FromJsArgs, R: NapiValueT> {
     pub utf8name: Option<String>,
@@ -43,7 +171,6 @@ pub struct DescriptorMethodBuilder<T: FromJsArgs, R: NapiValueT> {
 }
 
 /// The DescriptorBuild for accessor.
-/// NB: there seems no way to reclaim the napi_property_descriptor.data, so it is leaked.
 #[allow(clippy::type_complexity)]
 pub struct DescriptorAccessorBuilder<T: NapiValueT, R: NapiValueT> {
     pub utf8name: Option<String>,
@@ -101,13 +228,14 @@ impl DescriptorValueBuilder {
 
     /// build finale `NapiPropertyDescriptor`
     pub fn build(mut self) -> NapiResult<NapiPropertyDescriptor> {
-        let utf8name = if let Some(name) = self.utf8name {
-            std::ffi::CString::new(name)
-                .map_err(|_| NapiStatus::StringExpected)?
-                .into_raw()
-        } else {
-            std::ptr::null()
-        };
+        let mut owned = Owned::default();
+        if let Some(name) = self.utf8name.take() {
+            owned.utf8name = Some(CString::new(name).map_err(|_| NapiStatus::StringExpected)?);
+        }
+        let utf8name = owned
+            .utf8name
+            .as_ref()
+            .map_or(std::ptr::null(), |name| name.as_ptr());
 
         let name = self.name;
 
@@ -123,16 +251,19 @@ impl DescriptorValueBuilder {
         let value = self.value;
         let attributes = self.attributes.bits();
 
-        Ok(NapiPropertyDescriptor(napi_property_descriptor {
-            utf8name,
-            name,
-            method,
-            getter,
-            setter,
-            value,
-            attributes,
-            data: std::ptr::null_mut(),
-        }))
+        Ok(NapiPropertyDescriptor {
+            raw: napi_property_descriptor {
+                utf8name,
+                name,
+                method,
+                getter,
+                setter,
+                value,
+                attributes,
+                data: std::ptr::null_mut(),
+            },
+            owned: Rc::new(owned),
+        })
     }
 }
 
@@ -188,13 +319,14 @@ impl<T: FromJsArgs, R: NapiValueT> DescriptorMethodBuilder<T, R> {
     /// build finale `NapiPropertyDescriptor`
     #[allow(clippy::type_complexity)]
     pub fn build(mut self) -> NapiResult<NapiPropertyDescriptor> {
-        let utf8name = if let Some(name) = self.utf8name {
-            std::ffi::CString::new(name)
-                .map_err(|_| NapiStatus::StringExpected)?
-                .into_raw()
-        } else {
-            std::ptr::null()
-        };
+        let mut owned = Owned::default();
+        if let Some(name) = self.utf8name.take() {
+            owned.utf8name = Some(CString::new(name).map_err(|_| NapiStatus::StringExpected)?);
+        }
+        let utf8name = owned
+            .utf8name
+            .as_ref()
+            .map_or(std::ptr::null(), |name| name.as_ptr());
 
         let name = self.name;
 
@@ -207,45 +339,20 @@ impl<T: FromJsArgs, R: NapiValueT> DescriptorMethodBuilder<T, R> {
             env: NapiEnv,
             info: napi_callback_info,
         ) -> napi_value {
-            let mut data = MaybeUninit::uninit();
-            let mut this = MaybeUninit::uninit();
-
-            let (argc, argv, this, mut func) = unsafe {
-                let mut argc = T::len();
-                let mut argv = vec![std::ptr::null_mut(); T::len()];
-
-                let status = api::napi_get_cb_info(
-                    env,
-                    info,
-                    &mut argc,
-                    argv.as_mut_ptr(),
-                    this.as_mut_ptr(),
-                    data.as_mut_ptr(),
-                );
-
-                let func: &mut Box<dyn FnMut(JsObject, T) -> NapiResult<R>> =
-                    std::mem::transmute(data);
-
-                (argc, argv, this.assume_init(), func)
-            };
-
-            let args = argv
-                .into_iter()
-                .map(|arg| JsValue::from_raw(env, arg))
-                .collect();
-            let this = JsObject::from_raw(env, this);
-
-            if let Ok(args) = T::from_js_args(JsArgs(args)) {
-                napi_r!(env, =func(this, args))
-            } else {
-                env.throw_error("wrong argument type!").unwrap();
-                env.undefined().unwrap().raw()
-            }
+            crate::callback::invoke(env, || {
+                let info = CallbackInfo::from_raw(env, info);
+                let (this, args, data) = info.get(T::len())?;
+                // NB: the closure lives as long as the function
+                let func =
+                    unsafe { &mut *(data as *mut Box<dyn FnMut(JsObject, T) -> NapiResult<R>>) };
+                let args = crate::args::from_call::<T>(env, args)?;
+                Ok(func(this, args)?.raw())
+            })
         }
 
         let method = Some(method_trampoline::<T, R> as _);
         let data = if let Some(method) = self.method.take() {
-            Box::into_raw(Box::new(method)) as _
+            owned.data.insert(Data::new(method)).pointer
         } else {
             return Err(NapiStatus::InvalidArg);
         };
@@ -256,16 +363,19 @@ impl<T: FromJsArgs, R: NapiValueT> DescriptorMethodBuilder<T, R> {
 
         let attributes = self.attributes.bits();
 
-        Ok(NapiPropertyDescriptor(napi_property_descriptor {
-            utf8name,
-            name,
-            method,
-            getter,
-            setter,
-            value,
-            attributes,
-            data,
-        }))
+        Ok(NapiPropertyDescriptor {
+            raw: napi_property_descriptor {
+                utf8name,
+                name,
+                method,
+                getter,
+                setter,
+                value,
+                attributes,
+                data,
+            },
+            owned: Rc::new(owned),
+        })
     }
 }
 
@@ -333,13 +443,14 @@ impl<T: NapiValueT, R: NapiValueT> DescriptorAccessorBuilder<T, R> {
     /// build finale `NapiPropertyDescriptor`
     #[allow(clippy::type_complexity)]
     pub fn build(mut self) -> NapiResult<NapiPropertyDescriptor> {
-        let utf8name = if let Some(name) = self.utf8name {
-            std::ffi::CString::new(name)
-                .map_err(|_| NapiStatus::StringExpected)?
-                .into_raw()
-        } else {
-            std::ptr::null()
-        };
+        let mut owned = Owned::default();
+        if let Some(name) = self.utf8name.take() {
+            owned.utf8name = Some(CString::new(name).map_err(|_| NapiStatus::StringExpected)?);
+        }
+        let utf8name = owned
+            .utf8name
+            .as_ref()
+            .map_or(std::ptr::null(), |name| name.as_ptr());
 
         let name = self.name;
 
@@ -367,10 +478,6 @@ impl<T: NapiValueT, R: NapiValueT> DescriptorAccessorBuilder<T, R> {
                     data.as_mut_ptr(),
                 );
 
-                // NB: the Function maybe called multiple times, so we can shoud leak the
-                // closure memory here.
-                //
-                // With napi >= 5, we can add a finalizer to this function.
                 let func: &mut (
                     Option<Box<dyn FnMut(JsObject) -> NapiResult<R>>>,
                     Option<Box<dyn FnMut(JsObject, T) -> NapiResult<()>>>,
@@ -381,7 +488,7 @@ impl<T: NapiValueT, R: NapiValueT> DescriptorAccessorBuilder<T, R> {
 
             let this = JsObject::from_raw(env, this);
 
-            napi_r!(env, =func.0.as_mut().unwrap()(this))
+            crate::callback::invoke(env, || Ok(func.0.as_mut().unwrap()(this)?.raw()))
         }
 
         let mut data = (None, None);
@@ -420,10 +527,14 @@ impl<T: NapiValueT, R: NapiValueT> DescriptorAccessorBuilder<T, R> {
                 (argc, argv, this.assume_init(), func)
             };
 
-            let value = T::from_raw(env, argv[0]);
+            let value = JsValue::from_raw(env, argv[0]);
             let this = JsObject::from_raw(env, this);
 
-            napi_r!(env, func.1.as_mut().unwrap()(this, value))
+            crate::callback::invoke(env, || {
+                let value = T::from_js_args(JsArgs(vec![value]))?;
+                func.1.as_mut().unwrap()(this, value)?;
+                Ok(std::ptr::null_mut())
+            })
         }
 
         let setter = if let Some(setter) = self.setter {
@@ -438,16 +549,21 @@ impl<T: NapiValueT, R: NapiValueT> DescriptorAccessorBuilder<T, R> {
         let method = None;
         let value = std::ptr::null_mut();
 
-        Ok(NapiPropertyDescriptor(napi_property_descriptor {
-            utf8name,
-            name,
-            method,
-            getter,
-            setter,
-            value,
-            attributes,
-            data: Box::into_raw(Box::new(data)) as _,
-        }))
+        let data = owned.data.insert(Data::new(data)).pointer;
+
+        Ok(NapiPropertyDescriptor {
+            raw: napi_property_descriptor {
+                utf8name,
+                name,
+                method,
+                getter,
+                setter,
+                value,
+                attributes,
+                data,
+            },
+            owned: Rc::new(owned),
+        })
     }
 }
 
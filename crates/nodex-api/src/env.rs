@@ -5,6 +5,11 @@ use crate::{
     prelude::*,
 };
 
+#[cfg(feature = "v6")]
+use crate::state::{Finalizer, InstanceData, Registry};
+#[cfg(feature = "v3")]
+use std::rc::Rc;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct NapiEnv(pub(crate) napi_env);
@@ -95,6 +100,18 @@ impl NapiEnv {
         JsString::new(*self, s)
     }
 
+    /// Create a js string from ISO-8859-1-encoded bytes.
+    #[inline]
+    pub fn string_latin1(&self, s: impl AsRef<[u8]>) -> NapiResult<JsString> {
+        JsString::latin1(*self, s)
+    }
+
+    /// Create a js string from UTF16-encoded code units.
+    #[inline]
+    pub fn string_utf16(&self, s: impl AsRef<[u16]>) -> NapiResult<JsString> {
+        JsString::utf16(*self, s)
+    }
+
     /// Create an empty js array.
     #[inline]
     pub fn array(&self) -> NapiResult<JsArray> {
@@ -104,15 +121,22 @@ impl NapiEnv {
     #[cfg(feature = "v6")]
     /// Create a bigint_int64.
     #[inline]
-    pub fn bigint_i64(&self, value: i64) -> NapiResult<JsBigInt<i64>> {
-        JsBigInt::<i64>::new_i64(*self, value)
+    pub fn bigint_i64(&self, value: i64) -> NapiResult<JsBigInt> {
+        JsBigInt::new_i64(*self, value)
     }
 
     #[cfg(feature = "v6")]
     /// Create a bigint_unt64.
     #[inline]
-    pub fn bigint_u64(&self, value: u64) -> NapiResult<JsBigInt<u64>> {
-        JsBigInt::<u64>::new_u64(*self, value)
+    pub fn bigint_u64(&self, value: u64) -> NapiResult<JsBigInt> {
+        JsBigInt::new_u64(*self, value)
+    }
+
+    #[cfg(feature = "v6")]
+    /// Create a bigint from its sign and little-endian 64-bit words.
+    #[inline]
+    pub fn bigint_words(&self, negative: bool, words: &[u64]) -> NapiResult<JsBigInt> {
+        JsBigInt::from_words(*self, negative, words)
     }
 
     /// Create a boolean.
@@ -121,16 +145,16 @@ impl NapiEnv {
         JsBoolean::new(*self, boolean)
     }
 
-    /// Create a Buffer<N>
+    /// Create a Buffer of `len` zero bytes
     #[inline]
-    pub fn buffer<const N: usize>(&self) -> NapiResult<JsBuffer<N>> {
-        JsBuffer::<N>::create(*self)
+    pub fn buffer(&self, len: usize) -> NapiResult<JsBuffer> {
+        JsBuffer::new(*self, len)
     }
 
-    /// Create a Buffer<N> from [u8]
+    /// Create a Buffer from [u8]
     #[inline]
-    pub fn buffer_copy<const N: usize>(&self, data: [u8; N]) -> NapiResult<JsBuffer<N>> {
-        JsBuffer::<N>::create_copy(*self, data)
+    pub fn buffer_copy(&self, data: impl AsRef<[u8]>) -> NapiResult<JsBuffer> {
+        JsBuffer::from_slice(*self, data)
     }
 
     /// Create an ArrayBuffer
@@ -139,6 +163,28 @@ impl NapiEnv {
         JsArrayBuffer::new(*self, buffer)
     }
 
+    #[cfg(feature = "rt")]
+    /// Spawn a rust future on the async runtime, the returned promise is settled by its output.
+    #[inline]
+    pub fn spawn_future<F>(
+        &self,
+        future: F,
+    ) -> NapiResult<JsPromise<<F::Output as ToJs>::Value, JsValue>>
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: ToJs + Send + 'static,
+        <F::Output as ToJs>::Value: Copy + 'static,
+    {
+        crate::rt::spawn(*self, future)
+    }
+
+    /// Create a TypedArray of `len` zero elements, the element type decides which kind of
+    /// TypedArray it is.
+    #[inline]
+    pub fn typedarray<T: TypedArrayElement>(&self, len: usize) -> NapiResult<JsTypedArray<T>> {
+        JsTypedArray::<T>::new(*self, len)
+    }
+
     #[cfg(feature = "v5")]
     /// Create a Date.
     pub fn date(&self, time: f64) -> NapiResult<JsDate> {
@@ -186,12 +232,12 @@ impl NapiEnv {
     pub fn func<T: FromJsArgs, R>(
         &self,
         func: impl FnMut(JsObject, T) -> NapiResult<R> + 'static,
-    ) -> NapiResult<Function<R>>
+    ) -> NapiResult<Function<R::Value>>
     where
         T: FromJsArgs,
-        R: NapiValueT,
+        R: ToJs,
     {
-        Function::<R>::new(*self, Option::<String>::None, func)
+        Function::<R::Value>::new(*self, Option::<String>::None, func)
     }
 
     /// Create a named js function with a rust closure.
@@ -200,12 +246,23 @@ impl NapiEnv {
         &self,
         name: impl AsRef<str>,
         func: impl FnMut(JsObject, T) -> NapiResult<R> + 'static,
-    ) -> NapiResult<Function<R>>
+    ) -> NapiResult<Function<R::Value>>
     where
         T: FromJsArgs,
-        R: NapiValueT,
+        R: ToJs,
     {
-        Function::<R>::new(*self, Some(name), func)
+        Function::<R::Value>::new(*self, Some(name), func)
+    }
+
+    /// Create a js function with a rust closure taking the `CallContext` of each call, `data` is
+    /// shared by the calls as `CallContext::data()`.
+    #[inline]
+    pub fn func_with_context<D: 'static, R: ToJs>(
+        &self,
+        data: D,
+        func: impl FnMut(&CallContext<D>) -> NapiResult<R> + 'static,
+    ) -> NapiResult<Function<R::Value>> {
+        Function::<R::Value>::with_context(*self, Option::<String>::None, data, func)
     }
 
     /// Create a named js function with a rust function
@@ -264,6 +321,19 @@ impl NapiEnv {
         JsClass::new(*self, name, func, properties)
     }
 
+    /// Create a js class whose constructor is a rust closure taking the `CallContext` of each
+    /// call, `data` is shared by the calls as `CallContext::data()`.
+    #[inline]
+    pub fn class_with_context<D: 'static, R: NapiValueT>(
+        &self,
+        name: impl AsRef<str>,
+        data: D,
+        func: impl FnMut(&CallContext<D>) -> NapiResult<R> + 'static,
+        properties: impl AsRef<[NapiPropertyDescriptor]>,
+    ) -> NapiResult<JsClass> {
+        JsClass::with_context(*self, name, data, func, properties)
+    }
+
     /// Create an async work with shared state
     #[inline]
     pub fn async_work<T>(
@@ -316,13 +386,7 @@ impl NapiEnv {
         object: impl NapiValueT,
         properties: impl AsRef<[NapiPropertyDescriptor]>,
     ) -> NapiResult<()> {
-        napi_call!(
-            napi_define_properties,
-            *self,
-            object.raw(),
-            properties.as_ref().len(),
-            properties.as_ref().as_ptr() as *const _,
-        )
+        object.define_properties(properties)
     }
 
     /// This API throws the JavaScript value provided.
@@ -466,6 +530,27 @@ impl NapiEnv {
         Ok(task(self.handle_scope()?))
     }
 
+    /// Run `task` in a handle scope which binds the values to its lifetime, see `LocalScope`.
+    pub fn local_scope<R>(
+        &self,
+        task: impl for<'s> FnOnce(&mut LocalScope<'s>) -> R,
+    ) -> NapiResult<R> {
+        let _scope = self.handle_scope()?;
+        Ok(task(&mut LocalScope::new(*self)))
+    }
+
+    /// Run `task` in a `LocalScope`, the value it returns is promoted to the current handle
+    /// scope, e.g. to be returned from a js callback.
+    pub fn local_scope_escape<T: NapiValueT>(
+        &self,
+        task: impl for<'s> FnOnce(&mut LocalScope<'s>) -> NapiResult<Local<'s, T>>,
+    ) -> NapiResult<T> {
+        let mut scope = self.escapable_handle_scope()?;
+        let escapee = task(&mut LocalScope::new(*self))?;
+        // NB: the escaped value lives in the current scope, which is not a local scope
+        scope.escape(unsafe { escapee.into_inner() })
+    }
+
     /// Create a escapable handle scope
     #[inline]
     pub fn escapable_handle_scope(&self) -> NapiResult<NapiEscapableHandleScope> {
@@ -498,27 +583,34 @@ impl NapiEnv {
     #[inline]
     pub fn add_cleanup_hook<Hook>(&self, hook: Hook) -> NapiResult<CleanupHookHandler>
     where
-        Hook: FnOnce() -> NapiResult<()>,
+        Hook: FnOnce() -> NapiResult<()> + 'static,
     {
-        let hook: Box<Box<dyn FnOnce() -> NapiResult<()>>> = Box::new(Box::new(hook));
+        let hook: Rc<CleanupHook> = Rc::new(std::cell::RefCell::new(Some(Box::new(hook))));
 
         unsafe extern "C" fn cleanup_hook(data: *mut std::os::raw::c_void) {
             unsafe {
-                let hook: Box<Box<dyn FnOnce() -> NapiResult<()>>> = Box::from_raw(data as _);
-                if let Err(e) = hook() {
-                    log::error!("[{}] cleanup hook error.", e);
+                // NB: the hook is taken, so that the handler knows it has been called
+                let hook: Rc<CleanupHook> = Rc::from_raw(data as _);
+                let hook = hook.borrow_mut().take();
+                if let Some(hook) = hook {
+                    crate::callback::guard("cleanup hook", hook);
                 }
             }
         }
 
-        let args = Box::into_raw(hook) as _;
+        let args = Rc::into_raw(hook.clone()) as _;
 
-        napi_call!(napi_add_env_cleanup_hook, *self, Some(cleanup_hook), args);
+        let status = unsafe { api::napi_add_env_cleanup_hook(*self, Some(cleanup_hook), args) };
+        if status.err() {
+            unsafe { drop(Rc::from_raw(args as *const CleanupHook)) };
+            return Err(status);
+        }
 
         Ok(CleanupHookHandler {
             env: *self,
             hook: Some(cleanup_hook),
             args,
+            data: hook,
         })
     }
 
@@ -555,9 +647,9 @@ impl NapiEnv {
             unsafe {
                 let hook: Box<Box<dyn FnOnce(AsyncCleanupHookHandler) -> NapiResult<()>>> =
                     Box::from_raw(data as _);
-                if let Err(e) = hook(AsyncCleanupHookHandler(handle)) {
-                    log::error!("[{}] cleanup hook error.", e);
-                }
+                crate::callback::guard("async cleanup hook", || {
+                    hook(AsyncCleanupHookHandler(handle))
+                });
             }
         }
 
@@ -611,61 +703,110 @@ impl NapiEnv {
     }
 
     #[cfg(feature = "v6")]
-    #[allow(clippy::type_complexity)]
-    #[inline]
     /// This API associates data with the currently running Agent. data can later be retrieved
     /// using napi_get_instance_data(). Any existing data associated with the currently running
     /// Agent which was set by means of a previous call to napi_set_instance_data() will be
-    /// overwritten. If a finalize_cb was provided by the previous call, it will not be called.
+    /// overwritten.
+    ///
+    /// NB: the data is kept in the state registry of the env by its type, so data of other
+    /// types, e.g. set by another plugin, is not overwritten. The replaced data of type `T` is
+    /// passed to its finalizer, so the references returned by get_instance_data() for it must
+    /// not be used anymore.
     pub fn set_instance_data<T, F>(&self, data: T, finalizer: F) -> NapiResult<()>
     where
-        F: FnOnce(NapiEnv, T) -> NapiResult<()>,
+        T: 'static,
+        F: FnOnce(NapiEnv, T) -> NapiResult<()> + 'static,
     {
-        let data = Box::into_raw(Box::new(data)) as DataPointer;
-
-        // NB: Because we add a closure to the napi finalizer, it's better
-        // to **CAPTURE** the leaked data from rust side, so here we just
-        // ignore the passed in native data pointer.
-        unsafe extern "C" fn finalizer_trampoline<T>(
-            env: NapiEnv,
-            data: DataPointer,
-            finalizer: DataPointer,
-        ) {
-            // NB: here we collect the memory of finalizer closure
-            let finalizer: Box<Box<dyn FnOnce(NapiEnv, T) -> NapiResult<()>>> =
-                Box::from_raw(finalizer as _);
-
-            let data: Box<T> = Box::from_raw(data as _);
-
-            if let Err(err) = finalizer(env, *data) {
-                log::error!("NapiValueT::finalizer(): {}", err);
+        let data = Rc::new(InstanceData(std::cell::UnsafeCell::new(Some(data))));
+        let finalizer: Finalizer = Box::new(move |env, data| {
+            match data.downcast_ref::<InstanceData<T>>() {
+                // NB: the data is not borrowed anymore once the env is torn down
+                Some(data) => match unsafe { (*data.0.get()).take() } {
+                    Some(data) => finalizer(env, data),
+                    None => Ok(()),
+                },
+                None => Ok(()),
             }
-        }
-
-        let finalizer: Box<Box<dyn FnOnce(NapiEnv, T) -> NapiResult<()>>> =
-            Box::new(Box::new(finalizer));
-
-        napi_call!(
-            napi_set_instance_data,
-            *self,
-            data,
-            Some(finalizer_trampoline::<T>),
-            Box::into_raw(finalizer) as _,
-        )
+        });
+        let registry = Registry::of(*self)?;
+        registry.finalize::<InstanceData<T>>(*self);
+        registry.insert(data, Some(finalizer));
+        Ok(())
     }
 
     #[cfg(feature = "v6")]
     /// This API retrieves data that was previously associated with the currently running Agent via
     /// napi_set_instance_data(). If no data is set, the call will succeed and data will be set to
     /// NULL.
+    ///
+    /// NB: only data of type `T` is returned, the reference is valid until the data is replaced
+    /// by set_instance_data().
     #[inline]
-    pub fn get_instance_data<T>(&self) -> NapiResult<Option<&mut T>> {
-        let data = napi_call!(=napi_get_instance_data, *self) as *mut T;
-        if data.is_null() {
-            Ok(None)
-        } else {
-            unsafe { Ok(Some(&mut *data)) }
+    pub fn get_instance_data<T: 'static>(&self) -> NapiResult<Option<&mut T>> {
+        let data = Registry::of(*self)?.get::<InstanceData<T>>();
+        // NB: the data lives until it is replaced or the env is torn down
+        Ok(data.and_then(|data| unsafe { (*data.0.get()).as_mut() }))
+    }
+
+    #[cfg(feature = "v6")]
+    /// The state of type `T` of this env, None if it is not set.
+    ///
+    /// Each type has its own state, so plugins composed into one addon do not overwrite each
+    /// other's states, and each env, e.g. of a worker thread, has its own states.
+    pub fn state<T: 'static>(&self) -> NapiResult<Option<Rc<T>>> {
+        Ok(Registry::of(*self)?.get::<T>())
+    }
+
+    #[cfg(feature = "v6")]
+    /// The state of type `T` of this env, it is set by `init` if it is not set yet.
+    ///
+    /// NB: `init` may look up or set other states, if it sets the state of type `T` itself,
+    /// that state is kept and the one returned by `init` is dropped.
+    pub fn state_or_init<T: 'static>(
+        &self,
+        init: impl FnOnce() -> NapiResult<T>,
+    ) -> NapiResult<Rc<T>> {
+        let registry = Registry::of(*self)?;
+        if let Some(state) = registry.get::<T>() {
+            return Ok(state);
         }
+        let state = init()?;
+        if let Some(state) = registry.get::<T>() {
+            return Ok(state);
+        }
+        let state = Rc::new(state);
+        registry.insert(state.clone(), None);
+        Ok(state)
+    }
+
+    #[cfg(feature = "v6")]
+    /// Set the state of type `T` of this env, the replaced state is returned without being
+    /// finalized.
+    pub fn set_state<T: 'static>(&self, state: T) -> NapiResult<Option<Rc<T>>> {
+        Ok(Registry::of(*self)?.insert(Rc::new(state), None))
+    }
+
+    #[cfg(feature = "v6")]
+    /// Set the state of type `T` of this env, `finalizer` is called with the state when the env
+    /// is torn down. The finalizers are called in the reverse order of setting the states.
+    ///
+    /// The replaced state is returned without being finalized.
+    pub fn set_state_with_finalizer<T: 'static>(
+        &self,
+        state: T,
+        finalizer: impl FnOnce(NapiEnv, &T) -> NapiResult<()> + 'static,
+    ) -> NapiResult<Option<Rc<T>>> {
+        let finalizer: Finalizer = Box::new(move |env, state| match state.downcast_ref::<T>() {
+            Some(state) => finalizer(env, state),
+            None => Ok(()),
+        });
+        Ok(Registry::of(*self)?.insert(Rc::new(state), Some(finalizer)))
+    }
+
+    #[cfg(feature = "v6")]
+    /// Remove the state of type `T` of this env, it is returned without being finalized.
+    pub fn take_state<T: 'static>(&self) -> NapiResult<Option<Rc<T>>> {
+        Ok(Registry::of(*self)?.remove::<T>())
     }
 }
 
@@ -674,12 +815,26 @@ pub struct CleanupHookHandler {
     env: NapiEnv,
     hook: Option<unsafe extern "C" fn(data: *mut std::os::raw::c_void)>,
     args: *mut std::os::raw::c_void,
+    data: Rc<CleanupHook>,
 }
 
+#[cfg(feature = "v3")]
+type CleanupHook = std::cell::RefCell<Option<Box<dyn FnOnce() -> NapiResult<()>>>>;
+
 #[cfg(feature = "v3")]
 impl CleanupHookHandler {
+    /// Remove the hook, the hook is dropped without being called.
+    ///
+    /// NB: it does nothing if the hook has been called already.
     pub fn remove(self) -> NapiResult<()> {
-        napi_call!(napi_remove_env_cleanup_hook, self.env, self.hook, self.args)
+        if self.data.borrow().is_none() {
+            return Ok(());
+        }
+        napi_call!(napi_remove_env_cleanup_hook, self.env, self.hook, self.args);
+        unsafe {
+            drop(Rc::from_raw(self.args as *const CleanupHook));
+        }
+        Ok(())
     }
 }
 
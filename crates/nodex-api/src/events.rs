@@ -0,0 +1,302 @@
+//! Emit events to js listeners from rust
+//!
+//! An `Emitter` is attached to a js object, and emits events to its listeners from any thread
+//! through a threadsafe function:
+//!
+//! * if the object has an `emit` method, e.g. it is an `EventEmitter` or an instance of a js class
+//!   extending `EventEmitter`, the events are emitted by `object.emit(name, ...args)`
+//! * otherwise `on(name, listener)`, `once(name, listener)` and `off(name, listener)` are
+//!   installed on the object, and the listeners are kept by the emitter
+//!
+//! The events are queued until the js thread dispatches them. The queue is unbounded by default,
+//! with a limited queue `emit` blocks while the queue is full, and `try_emit` returns
+//! napi_queue_full instead. Once the env is torn down, the emitter is closed and emitting returns
+//! napi_closing.
+//!
+//! ```ignore
+//! use nodex::{events::Emitter, prelude::*};
+//!
+//! fn download(env: NapiEnv, target: JsObject) -> NapiResult<()> {
+//!     let emitter = Emitter::with_queue_size(target, 16)?;
+//!     std::thread::spawn(move || {
+//!         for percent in (0..=100).step_by(10) {
+//!             emitter.emit("progress", percent).unwrap();
+//!         }
+//!         emitter.emit("done", ()).unwrap();
+//!     });
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{env::CleanupHookHandler, prelude::*};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, RwLock},
+    thread::ThreadId,
+};
+
+/// Arguments of an event, converted to js values on the js thread.
+type EventArgs = Box<dyn FnOnce(NapiEnv) -> NapiResult<JsArgs> + Send>;
+
+struct Event {
+    name: String,
+    args: EventArgs,
+}
+
+/// Emit events to the listeners of a js object, it can be cloned and sent to other threads.
+///
+/// The object is kept alive until the last clone of the emitter is dropped.
+#[derive(Clone)]
+pub struct Emitter(Arc<Shared>);
+
+struct Shared {
+    tsfn: NapiTsfn<Event>,
+    // NB: true once the threadsafe function is aborted, released or finalized, it is read locked
+    // across the napi calls, so the function is not freed meanwhile
+    closed: Arc<RwLock<bool>>,
+    thread: ThreadId,
+}
+
+impl Emitter {
+    /// Attach an emitter to `target` with an unbounded queue.
+    pub fn new(target: JsObject) -> NapiResult<Emitter> {
+        Emitter::with_queue_size(target, 0)
+    }
+
+    /// Attach an emitter to `target`, at most `max_queue_size` events are queued, 0 for no
+    /// limit.
+    pub fn with_queue_size(target: JsObject, max_queue_size: usize) -> NapiResult<Emitter> {
+        let env = target.env();
+
+        let listeners = if target.get_named_property("emit")?.is_function()? {
+            None
+        } else {
+            Some(Listeners::install(target)?)
+        };
+
+        let target = Persistent::new(target)?;
+        let hook: Rc<Cell<Option<CleanupHookHandler>>> = Rc::new(Cell::new(None));
+        let closed = Arc::new(RwLock::new(false));
+
+        let tsfn: NapiTsfn<Event> = NapiTsfn::with_queue_size(
+            env,
+            "nodex-emitter",
+            env.func(|this, ()| this.env().undefined())?,
+            max_queue_size,
+            {
+                let hook = hook.clone();
+                let closed = closed.clone();
+                move |_| {
+                    *closed.write().unwrap() = true;
+                    match hook.take() {
+                        Some(hook) => hook.remove(),
+                        None => Ok(()),
+                    }
+                }
+            },
+            move |_, event: Event| {
+                let target = target.get()?;
+                let args = (event.args)(target.env())?;
+                match listeners.as_ref() {
+                    Some(listeners) => listeners.emit(target, &event.name, args),
+                    None => {
+                        let emit: JsFunction = target.get("emit")?;
+                        let mut all = vec![target.env().string(&event.name)?.value()];
+                        all.extend(args.0);
+                        emit.call(target, JsArgs(all))?;
+                        Ok(())
+                    }
+                }
+            },
+        )?;
+
+        // NB: abort the threadsafe function when the env is torn down, the blocked emitters
+        // are woken up then.
+        hook.set(Some(env.add_cleanup_hook({
+            let tsfn = NapiTsfn::<Event>::from_raw(env, tsfn.raw());
+            let closed = closed.clone();
+            let hook = hook.clone();
+            move || {
+                hook.take();
+                let aborted = {
+                    // NB: the blocked emitters hold the read lock as well, they are woken up by
+                    // the abort, so the write lock is taken after it
+                    let closed = closed.read().unwrap();
+                    if *closed {
+                        return Ok(());
+                    }
+                    tsfn.abort()
+                };
+                *closed.write().unwrap() = true;
+                aborted
+            }
+        })?));
+
+        Ok(Emitter(Arc::new(Shared {
+            tsfn,
+            closed,
+            thread: std::thread::current().id(),
+        })))
+    }
+
+    /// Emit an event, `args` are passed to the listeners as separate arguments, e.g. a tuple
+    /// `(a, b)` is passed as `listener(a, b)`.
+    ///
+    /// It blocks while the queue is full, except on the js thread, where napi_queue_full is
+    /// returned to avoid a deadlock.
+    pub fn emit<A>(&self, name: impl Into<String>, args: A) -> NapiResult<()>
+    where
+        A: ToJsArgs + Send + 'static,
+    {
+        let mode = if std::thread::current().id() == self.0.thread {
+            NapiTsfnMode::Nonblocking
+        } else {
+            NapiTsfnMode::Blocking
+        };
+        self.send(name.into(), args, mode)
+    }
+
+    /// Emit an event, napi_queue_full is returned if the queue is full.
+    pub fn try_emit<A>(&self, name: impl Into<String>, args: A) -> NapiResult<()>
+    where
+        A: ToJsArgs + Send + 'static,
+    {
+        self.send(name.into(), args, NapiTsfnMode::Nonblocking)
+    }
+
+    /// The env is torn down, no more events can be emitted.
+    pub fn is_closed(&self) -> bool {
+        *self.0.closed.read().unwrap()
+    }
+
+    /// Let the event loop exit while the emitter is alive, it may only be called on the js
+    /// thread.
+    pub fn unref(&self) -> NapiResult<()> {
+        self.0.tsfn.unref()
+    }
+
+    /// Keep the event loop alive while the emitter is alive, which is the default, it may only
+    /// be called on the js thread.
+    pub fn refer(&self) -> NapiResult<()> {
+        self.0.tsfn.refer()
+    }
+
+    fn send<A>(&self, name: String, args: A, mode: NapiTsfnMode) -> NapiResult<()>
+    where
+        A: ToJsArgs + Send + 'static,
+    {
+        let closed = self.0.closed.read().unwrap();
+        if *closed {
+            return Err(NapiStatus::Closing);
+        }
+        let args: EventArgs = Box::new(move |env| args.to_js_args(env));
+        self.0.tsfn.call(Event { name, args }, mode)
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        let mut closed = self.closed.write().unwrap();
+        if *closed {
+            return;
+        }
+        *closed = true;
+        let tsfn = NapiTsfn::<Event>::from_raw(self.tsfn.env(), self.tsfn.raw());
+        if let Err(e) = tsfn.release() {
+            log::warn!("nodex::events::Emitter: {}", e);
+        }
+    }
+}
+
+/// Listeners registered by `on` and `once`, only used on the js thread.
+#[derive(Default)]
+struct Listeners(RefCell<HashMap<String, Vec<Listener>>>);
+
+struct Listener {
+    callback: Persistent<JsFunction>,
+    once: bool,
+}
+
+impl Listeners {
+    /// Install `on`, `once` and `off` on the target.
+    fn install(mut target: JsObject) -> NapiResult<Rc<Listeners>> {
+        let env = target.env();
+        let listeners = Rc::new(Listeners::default());
+
+        for (method, once) in [("on", false), ("once", true)] {
+            let listeners = listeners.clone();
+            target.set_named_property(
+                method,
+                env.func(move |this, (name, callback): (String, JsFunction)| {
+                    listeners.add(name, callback, once)?;
+                    Ok(this)
+                })?,
+            )?;
+        }
+
+        let off = listeners.clone();
+        target.set_named_property(
+            "off",
+            env.func(move |this, (name, callback): (String, JsFunction)| {
+                off.remove(&name, callback)?;
+                Ok(this)
+            })?,
+        )?;
+
+        Ok(listeners)
+    }
+
+    fn add(&self, name: String, callback: JsFunction, once: bool) -> NapiResult<()> {
+        let callback = Persistent::new(callback)?;
+        self.0
+            .borrow_mut()
+            .entry(name)
+            .or_default()
+            .push(Listener { callback, once });
+        Ok(())
+    }
+
+    /// Remove the first listener of `name` which is `callback`.
+    fn remove(&self, name: &str, callback: JsFunction) -> NapiResult<()> {
+        let mut listeners = self.0.borrow_mut();
+        let Some(list) = listeners.get_mut(name) else {
+            return Ok(());
+        };
+        for index in 0..list.len() {
+            if list[index].callback.get()?.equals(callback)? {
+                list.remove(index);
+                break;
+            }
+        }
+        if list.is_empty() {
+            listeners.remove(name);
+        }
+        Ok(())
+    }
+
+    fn emit(&self, target: JsObject, name: &str, args: JsArgs) -> NapiResult<()> {
+        // NB: the listeners may be changed by the listeners, so they are collected first
+        let callbacks = {
+            let mut listeners = self.0.borrow_mut();
+            let Some(list) = listeners.get_mut(name) else {
+                return Ok(());
+            };
+            let callbacks = list
+                .iter()
+                .map(|listener| listener.callback.get())
+                .collect::<NapiResult<Vec<_>>>()?;
+            list.retain(|listener| !listener.once);
+            if list.is_empty() {
+                listeners.remove(name);
+            }
+            callbacks
+        };
+
+        for callback in callbacks {
+            callback.call(target, args.clone())?;
+        }
+        Ok(())
+    }
+}
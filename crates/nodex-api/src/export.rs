@@ -0,0 +1,49 @@
+use crate::{api, prelude::*};
+use std::collections::HashSet;
+
+/// A named js value which is registered at link time, e.g. by `#[nodex::function]`, and
+/// installed on the module exports by `napi_module!`.
+pub struct NapiExport {
+    name: &'static str,
+    create: fn(NapiEnv) -> NapiResult<JsValue>,
+}
+
+inventory::collect!(NapiExport);
+
+impl NapiExport {
+    /// Create a registration entry, `create` is called once per module initialization.
+    pub const fn new(name: &'static str, create: fn(NapiEnv) -> NapiResult<JsValue>) -> Self {
+        NapiExport { name, create }
+    }
+
+    /// The property name on the module exports.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Create the js value of this export.
+    pub fn create(&self, env: NapiEnv) -> NapiResult<JsValue> {
+        (self.create)(env)
+    }
+
+    /// All the registered exports.
+    pub fn iter() -> impl Iterator<Item = &'static NapiExport> {
+        inventory::iter::<NapiExport>.into_iter()
+    }
+}
+
+/// Set all the registered exports on the `exports` object, an Error is thrown if two exports
+/// share a name.
+pub fn install(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
+    let mut names = HashSet::new();
+    for export in NapiExport::iter() {
+        if !names.insert(export.name()) {
+            return Err(crate::callback::throw_error(
+                env,
+                format!("nodex: duplicate export name `{}`", export.name()),
+            ));
+        }
+        exports.set(export.name(), export.create(env)?)?;
+    }
+    Ok(())
+}
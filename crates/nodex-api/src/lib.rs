@@ -64,20 +64,23 @@ pub enum NapiKeyCollectionMode {
     KeyOwnOnly = api::napi_key_collection_mode_napi_key_own_only,
 }
 
-/// napi_key_filter
-#[repr(u32)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum NapiKeyFilter {
-    KeyAllProperties = api::napi_key_filter_napi_key_all_properties,
-    Writable = api::napi_key_filter_napi_key_writable,
-    Enumerable = api::napi_key_filter_napi_key_enumerable,
-    Configurable = api::napi_key_filter_napi_key_configurable,
-    SkipStrings = api::napi_key_filter_napi_key_skip_strings,
-    SkipSymbols = api::napi_key_filter_napi_key_skip_symbols,
+bitflags::bitflags! {
+    /// napi_key_filter
+    #[repr(transparent)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct NapiKeyFilter: u32 {
+        const KeyAllProperties = api::napi_key_filter_napi_key_all_properties;
+        const Writable = api::napi_key_filter_napi_key_writable;
+        const Enumerable = api::napi_key_filter_napi_key_enumerable;
+        const Configurable = api::napi_key_filter_napi_key_configurable;
+        const SkipStrings = api::napi_key_filter_napi_key_skip_strings;
+        const SkipSymbols = api::napi_key_filter_napi_key_skip_symbols;
+    }
 }
 
 /// napi_key_conversion
 #[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum NapiKeyConversion {
     KeepNumbers = api::napi_key_conversion_napi_key_keep_numbers,
     NumbersToStrings = api::napi_key_conversion_napi_key_numbers_to_strings,
@@ -104,12 +107,24 @@ pub enum NapiThreadsafeFunctionCallMode {
 pub mod args;
 pub mod callback;
 pub mod context;
+pub mod convert;
 pub mod descriptor;
 pub mod env;
 mod error;
+#[cfg(feature = "v4")]
+pub mod events;
+pub mod export;
+pub mod module;
+pub mod plugin;
 pub mod reference;
+#[cfg(feature = "rt")]
 pub mod rt;
 pub mod scope;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "v6")]
+mod state;
+pub mod tag;
 #[cfg(feature = "v4")]
 pub mod tsfn;
 pub mod value;
@@ -118,6 +133,13 @@ pub mod work;
 // napi status and result type
 pub use error::{NapiResult, NapiStatus};
 
+#[doc(hidden)]
+pub mod __private {
+    // used by nodex-macros
+    pub use crate::value::class::take_native_instance;
+    pub use inventory;
+}
+
 pub mod prelude {
     use super::*;
 
@@ -140,11 +162,13 @@ pub mod prelude {
     pub use error::{NapiResult, NapiStatus};
 
     pub use args::*;
-    pub use callback::CallbackInfo;
+    pub use callback::{CallContext, CallbackInfo};
     pub use context::NapiAsyncContext;
+    pub use convert::{FromJs, ToJs};
     pub use descriptor::*;
-    pub use reference::NapiRef;
+    pub use reference::{NapiRef, Persistent, Weak};
     pub use scope::*;
+    pub use tag::TypeTagError;
     #[cfg(feature = "v4")]
     pub use tsfn::*;
     pub use value::*;
@@ -11,22 +11,58 @@ macro_rules! napi_module {
             let env = $crate::env::NapiEnv::from_raw(env);
             let exports = $crate::value::JsObject::from_raw(env, exports);
 
-            // TODO: deal with exception
-            match std::panic::catch_unwind(move || $init(env, exports)) {
-                Ok(Ok(_)) => {}
-                Ok(Err(e)) => {
-                    env.throw_error(format!("napi: {:?}", e)).unwrap();
-                }
-                Err(e) => {
-                    env.throw_error(format!("panic: {:?}", e)).unwrap();
-                }
-            }
+            // NB: an error or a panic is thrown, then `require` throws it
+            $crate::callback::invoke(env, move || {
+                $crate::module::init(env, exports, $init)?;
+                Ok(exports.raw())
+            })
+        }
+    };
+}
+
+/// Register an item at link time, e.g. a `NapiExport` or a `NapiPluginRegistration`.
+///
+/// NB: a registration references napi symbols which are only resolved when the addon is loaded
+/// by node, so it is left out of the test builds of the crate registering it.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __napi_register {
+    ($item:expr) => {
+        #[cfg(not(test))]
+        $crate::__private::inventory::submit! {
+            $item
+        }
+    };
+}
 
-            exports.raw()
+/// Register a `NapiPlugin`, it is installed by `napi_module!` of the addon linking it.
+#[macro_export]
+macro_rules! napi_plugin {
+    ($plugin:expr) => {
+        $crate::__napi_register! {
+            $crate::plugin::NapiPluginRegistration::new(&$plugin)
         }
     };
 }
 
+/// Link plugin crates into the addon, so that `napi_module!` installs them.
+///
+/// NB: rustc does not link a crate which is never used, and the plugins are left out of the test
+/// builds of the addon, as they reference napi symbols which are only resolved by node.
+///
+/// ```ignore
+/// nodex::link_plugin!(nodex_plugin_helloworld);
+/// ```
+#[macro_export]
+macro_rules! link_plugin {
+    ($($krate:ident),+ $(,)?) => {
+        $(
+            #[cfg(not(test))]
+            use $krate as _;
+        )+
+    };
+}
+
 #[macro_export]
 macro_rules! napi_call {
     // [out] result: napi function which has output
@@ -114,7 +150,10 @@ macro_rules! napi_r {
         match $s {
             Ok(result) => result.raw(),
             Err(err) => {
-                $env.throw_error(format!("{}", err)).unwrap();
+                // NB: the callback may have thrown already
+                if !$env.is_exception_pending().unwrap_or(true) {
+                    $env.throw_error(format!("{}", err)).unwrap();
+                }
                 $env.undefined().unwrap().raw()
             }
         }
@@ -124,7 +163,10 @@ macro_rules! napi_r {
         match $s {
             Ok(result) => $env.undefined().unwrap().raw(),
             Err(err) => {
-                $env.throw_error(format!("{}", err)).unwrap();
+                // NB: the callback may have thrown already
+                if !$env.is_exception_pending().unwrap_or(true) {
+                    $env.throw_error(format!("{}", err)).unwrap();
+                }
                 $env.undefined().unwrap().raw()
             }
         }
@@ -135,7 +177,7 @@ macro_rules! napi_r {
 macro_rules! napi_as {
     ($self:ident, $T:ty, $err:expr) => {{
         let casted = unsafe { $self.cast::<$T>() };
-        if $self.check()? {
+        if casted.check()? {
             Ok(casted)
         } else {
             Err($err)
@@ -149,3 +191,8 @@ macro_rules! napi_is {
         unsafe { $self.cast::<$T>() }.check()
     };
 }
+
+macro_rules! count {
+    () => (0_usize);
+    ($x:tt $($xs:tt)*) => (1_usize + count!($($xs)*));
+}
@@ -0,0 +1,139 @@
+//! Module initialization per env
+//!
+//! An addon is loaded once per process, but initialized once per env: the main thread and every
+//! `worker_threads` Worker requiring it get their own env, and the env of a worker is torn down
+//! when the worker exits. `napi_module!` accepts an init function taking a `ModuleContext`,
+//! which tells the envs apart and registers the hooks run when the addon is unloaded from an env.
+//!
+//! ```ignore
+//! use nodex::{module::ModuleContext, prelude::*};
+//!
+//! nodex::napi_module!(init);
+//!
+//! fn init(context: &ModuleContext, mut exports: JsObject) -> NapiResult<()> {
+//!     let id = context.id();
+//!     context.on_unload(move |_| {
+//!         println!("addon unloaded from env {}", id);
+//!         Ok(())
+//!     })?;
+//!     exports.set_named_property("id", context.env().uint32(id as u32)?)
+//! }
+//! ```
+//!
+//! The states of the addon should be kept per env as well, e.g. by `NapiEnv::state_or_init`,
+//! as a js value only lives in the env it is created in. In debug builds, using a js value in a
+//! callback of another env panics, and the panic is thrown to js as an Error.
+
+#[cfg(feature = "v8")]
+use crate::env::AsyncCleanupHookHandler;
+use crate::prelude::*;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// The number of envs which initialized the addon so far.
+static INITIALIZED: AtomicU64 = AtomicU64::new(0);
+
+/// The number of envs in which the addon is loaded.
+static LOADED: AtomicUsize = AtomicUsize::new(0);
+
+/// The context of initializing the addon in an env.
+#[derive(Debug)]
+pub struct ModuleContext {
+    env: NapiEnv,
+    id: u64,
+}
+
+impl ModuleContext {
+    fn new(env: NapiEnv) -> NapiResult<ModuleContext> {
+        // NB: the cleanup hooks are called in reverse order, so this one is called after the
+        // unload hooks of the addon
+        #[cfg(feature = "v3")]
+        env.add_cleanup_hook(|| {
+            LOADED.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        })?;
+        LOADED.fetch_add(1, Ordering::SeqCst);
+
+        Ok(ModuleContext {
+            env,
+            id: INITIALIZED.fetch_add(1, Ordering::SeqCst),
+        })
+    }
+
+    /// `NapiEnv` the addon is initialized in.
+    pub fn env(&self) -> NapiEnv {
+        self.env
+    }
+
+    /// The sequence number of the env among the envs which initialized the addon, starting
+    /// from 0. It is never reused in the process.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The number of envs in which the addon is loaded, including this one.
+    ///
+    /// NB: without the `v3` feature, the envs which are torn down are counted as well.
+    pub fn loaded() -> usize {
+        LOADED.load(Ordering::SeqCst)
+    }
+
+    #[cfg(feature = "v3")]
+    /// Run `hook` when the addon is unloaded from this env, i.e. the env is torn down. The
+    /// hooks are run in reverse order of registration.
+    pub fn on_unload(
+        &self,
+        hook: impl FnOnce(NapiEnv) -> NapiResult<()> + 'static,
+    ) -> NapiResult<()> {
+        let env = self.env;
+        env.add_cleanup_hook(move || hook(env))?;
+        Ok(())
+    }
+
+    #[cfg(feature = "v8")]
+    /// Run `hook` when the addon is unloaded from this env, the hook may clean up
+    /// asynchronously, and removes the handler when it is done, which lets the env go.
+    pub fn on_unload_async(
+        &self,
+        hook: impl FnOnce(NapiEnv, AsyncCleanupHookHandler) -> NapiResult<()> + 'static,
+    ) -> NapiResult<()> {
+        let env = self.env;
+        env.add_async_cleanup_hook(move |handler| hook(env, handler))?;
+        Ok(())
+    }
+}
+
+/// An init function of `napi_module!`, taking either the `NapiEnv` or the `ModuleContext`.
+pub trait ModuleInit<Context> {
+    fn init(self, context: &ModuleContext, exports: JsObject) -> NapiResult<()>;
+}
+
+impl<F> ModuleInit<NapiEnv> for F
+where
+    F: FnOnce(NapiEnv, JsObject) -> NapiResult<()>,
+{
+    fn init(self, context: &ModuleContext, exports: JsObject) -> NapiResult<()> {
+        self(context.env(), exports)
+    }
+}
+
+impl<F> ModuleInit<ModuleContext> for F
+where
+    F: FnOnce(&ModuleContext, JsObject) -> NapiResult<()>,
+{
+    fn init(self, context: &ModuleContext, exports: JsObject) -> NapiResult<()> {
+        self(context, exports)
+    }
+}
+
+/// Initialize the addon in `env`, called by `napi_module!`: the registered exports and plugins
+/// are installed before `init` is called.
+pub fn init<Context>(
+    env: NapiEnv,
+    exports: JsObject,
+    init: impl ModuleInit<Context>,
+) -> NapiResult<()> {
+    let context = ModuleContext::new(env)?;
+    crate::export::install(env, exports)?;
+    crate::plugin::install(&context, exports)?;
+    init.init(&context, exports)
+}
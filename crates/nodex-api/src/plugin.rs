@@ -0,0 +1,113 @@
+//! Plugins registered at link time
+//!
+//! A plugin crate implements `NapiPlugin` and registers it by `napi_plugin!`, then depending on
+//! the crate is enough: `napi_module!` installs the exports of each plugin on a namespace
+//! object, i.e. `exports[plugin.name()]`, before the init function of the addon is called.
+//!
+//! ```ignore
+//! use nodex::{module::ModuleContext, plugin::NapiPlugin, prelude::*};
+//!
+//! pub struct HelloWorld;
+//!
+//! impl NapiPlugin for HelloWorld {
+//!     fn name(&self) -> &'static str {
+//!         "helloworld"
+//!     }
+//!
+//!     fn init(&self, context: &ModuleContext, mut exports: JsObject) -> NapiResult<()> {
+//!         exports.set_named_property("hello", context.env().string("hello, nodex!")?)
+//!     }
+//! }
+//!
+//! nodex::napi_plugin!(HelloWorld);
+//! ```
+//!
+//! The plugins are installed in the order of their names, and their namespaces are read-only,
+//! so the init function of the addon can not replace them. Loading the addon fails with an Error
+//! if a plugin requires a higher napi version than the running node offers, or if its name is
+//! taken by another plugin or a registered export.
+//!
+//! NB: rustc does not link a crate which is never used, so the addon pulls the plugin in by
+//! `link_plugin!`:
+//!
+//! ```ignore
+//! nodex::link_plugin!(nodex_plugin_helloworld);
+//! ```
+
+use crate::{module::ModuleContext, prelude::*};
+use std::collections::HashSet;
+
+/// A plugin which installs its exports on a namespace of the module exports.
+pub trait NapiPlugin: Sync {
+    /// The name of the plugin, which is the name of its namespace.
+    fn name(&self) -> &'static str;
+
+    /// The napi version the plugin requires.
+    fn napi_version(&self) -> u32 {
+        1
+    }
+
+    /// Install the exports of the plugin on its namespace object.
+    fn init(&self, context: &ModuleContext, exports: JsObject) -> NapiResult<()>;
+}
+
+/// A plugin registered at link time by `napi_plugin!`.
+pub struct NapiPluginRegistration(&'static dyn NapiPlugin);
+
+inventory::collect!(NapiPluginRegistration);
+
+impl NapiPluginRegistration {
+    /// Create a registration entry of `plugin`.
+    pub const fn new(plugin: &'static dyn NapiPlugin) -> Self {
+        NapiPluginRegistration(plugin)
+    }
+
+    /// The registered plugins, in the order of their names.
+    pub fn iter() -> impl Iterator<Item = &'static dyn NapiPlugin> {
+        let mut plugins = inventory::iter::<NapiPluginRegistration>
+            .into_iter()
+            .map(|registration| registration.0)
+            .collect::<Vec<_>>();
+        plugins.sort_by_key(|plugin| plugin.name());
+        plugins.into_iter()
+    }
+}
+
+/// Install the exports of all the registered plugins on the `exports` object.
+pub fn install(context: &ModuleContext, mut exports: JsObject) -> NapiResult<()> {
+    let env = context.env();
+    let available = env.napi_version()?;
+    let mut names = crate::export::NapiExport::iter()
+        .map(|export| export.name())
+        .collect::<HashSet<_>>();
+
+    for plugin in NapiPluginRegistration::iter() {
+        let name = plugin.name();
+        if plugin.napi_version() > available {
+            return Err(crate::callback::throw_error(
+                env,
+                format!(
+                    "nodex plugin {} requires napi {}, but napi {} is available",
+                    name,
+                    plugin.napi_version(),
+                    available,
+                ),
+            ));
+        }
+        if !names.insert(name) {
+            return Err(crate::callback::throw_error(
+                env,
+                format!("nodex plugin {}: duplicate export name `{}`", name, name),
+            ));
+        }
+
+        let namespace = env.object()?;
+        plugin.init(context, namespace)?;
+        exports.define_properties([DescriptorValueBuilder::new()
+            .with_utf8name(name)
+            .with_value(namespace)
+            .with_attribute(NapiPropertyAttributes::Enumerable)
+            .build()?])?;
+    }
+    Ok(())
+}
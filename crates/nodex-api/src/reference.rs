@@ -1,4 +1,11 @@
 use crate::{api, prelude::*};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    thread::ThreadId,
+};
 
 #[derive(Clone, Debug)]
 pub struct NapiRef(NapiEnv, napi_ref);
@@ -48,3 +55,224 @@ impl Drop for NapiRef {
         }
     }
 }
+
+/// A reference which can be moved to other threads, but only be used on the thread of its env.
+#[derive(Debug)]
+struct Reference {
+    env: NapiEnv,
+    raw: napi_ref,
+    thread: ThreadId,
+    tracker: Arc<Tracker>,
+}
+
+unsafe impl Send for Reference {}
+unsafe impl Sync for Reference {}
+
+/// The references of an env: the references dropped off the env thread, which are deleted by
+/// the env thread later, or None once the env is torn down, when all its references are gone.
+#[derive(Debug, Default)]
+struct Tracker(Mutex<Option<Vec<Orphan>>>);
+
+#[derive(Debug)]
+struct Orphan(napi_ref);
+
+unsafe impl Send for Orphan {}
+
+thread_local! {
+    /// the tracker of each env on this thread
+    static TRACKERS: RefCell<HashMap<usize, Arc<Tracker>>> = RefCell::new(HashMap::new());
+}
+
+impl Tracker {
+    /// The tracker of `env`, created on first use.
+    ///
+    /// NB: without the `v3` feature the teardown of an env is not observed, so the references
+    /// are never marked dead.
+    fn of(env: NapiEnv) -> NapiResult<Arc<Tracker>> {
+        let key = env.raw() as usize;
+        if let Some(tracker) = TRACKERS.with(|trackers| trackers.borrow().get(&key).cloned()) {
+            return Ok(tracker);
+        }
+
+        let tracker = Arc::new(Tracker(Mutex::new(Some(vec![]))));
+        // NB: delete the orphans and mark the references dead once the env is torn down, the
+        // pointer of the env may be reused by another env
+        #[cfg(feature = "v3")]
+        env.add_cleanup_hook({
+            let tracker = tracker.clone();
+            move || {
+                TRACKERS.with(|trackers| trackers.borrow_mut().remove(&key));
+                if let Some(orphans) = tracker.lock().take() {
+                    for Orphan(raw) in orphans {
+                        unsafe { api::napi_delete_reference(env, raw) };
+                    }
+                }
+                Ok(())
+            }
+        })?;
+        TRACKERS.with(|trackers| trackers.borrow_mut().insert(key, tracker.clone()));
+        Ok(tracker)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Vec<Orphan>>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Delete the references which are dropped off the env thread.
+    fn delete_orphans(&self, env: NapiEnv) {
+        let orphans = match self.lock().as_mut() {
+            Some(orphans) if !orphans.is_empty() => std::mem::take(orphans),
+            _ => return,
+        };
+        for Orphan(raw) in orphans {
+            unsafe { api::napi_delete_reference(env, raw) };
+        }
+    }
+}
+
+impl Reference {
+    fn new(env: NapiEnv, value: napi_value, count: u32) -> NapiResult<Reference> {
+        let tracker = Tracker::of(env)?;
+        tracker.delete_orphans(env);
+        let raw = napi_call!(=napi_create_reference, env, value, count);
+        Ok(Reference {
+            env,
+            raw,
+            thread: std::thread::current().id(),
+            tracker,
+        })
+    }
+
+    fn on_env_thread(&self) -> bool {
+        std::thread::current().id() == self.thread
+    }
+
+    /// The referenced value, None if it has been garbage-collected, napi_closing is returned
+    /// once the env is torn down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not called on the thread of the env.
+    fn value(&self) -> NapiResult<Option<napi_value>> {
+        assert!(
+            self.on_env_thread(),
+            "nodex: a reference can only be dereferenced on the thread of its env"
+        );
+        if self.tracker.lock().is_none() {
+            return Err(NapiStatus::Closing);
+        }
+        self.tracker.delete_orphans(self.env);
+        let value = napi_call!(=napi_get_reference_value, self.env, self.raw);
+        Ok(if value.is_null() { None } else { Some(value) })
+    }
+}
+
+impl Drop for Reference {
+    fn drop(&mut self) {
+        let mut orphans = self.tracker.lock();
+        // NB: the references are gone with the env
+        let Some(orphans) = orphans.as_mut() else {
+            return;
+        };
+        if self.on_env_thread() {
+            unsafe { api::napi_delete_reference(self.env, self.raw) };
+        } else {
+            // NB: napi can not be called off the env thread
+            orphans.push(Orphan(self.raw));
+        }
+    }
+}
+
+/// A strong reference to a js value, the value is not garbage-collected while it is alive.
+///
+/// `Persistent<T>` is `Send` and `Sync`, so it can be kept by long-lived rust state, e.g. a
+/// callback `Persistent<JsFunction>` in a registry shared with other threads, but it can only be
+/// dereferenced on the thread of its env, which is checked at runtime.
+///
+/// If it is dropped off the env thread, the reference is deleted by the env thread the next time
+/// a reference of the env is created or dereferenced, or when the env is torn down. Once the env
+/// is torn down, `get` returns napi_closing.
+///
+/// NB: for napi < 9 only objects, functions and symbols can be referenced.
+#[derive(Debug)]
+pub struct Persistent<T: NapiValueT> {
+    reference: Reference,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T: NapiValueT> Persistent<T> {
+    /// Create a strong reference to `value`.
+    pub fn new(value: T) -> NapiResult<Persistent<T>> {
+        Ok(Persistent {
+            reference: Reference::new(value.env(), value.raw(), 1)?,
+            _value: PhantomData,
+        })
+    }
+
+    /// `NapiEnv` of the referenced value
+    pub fn env(&self) -> NapiEnv {
+        self.reference.env
+    }
+
+    /// The referenced value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not called on the thread of the env.
+    pub fn get(&self) -> NapiResult<T> {
+        match self.reference.value()? {
+            Some(value) => Ok(T::from_raw(self.env(), value)),
+            // NB: a strong reference is never collected
+            None => Err(NapiStatus::GenericFailure),
+        }
+    }
+
+    /// Create a weak reference to the value.
+    pub fn downgrade(&self) -> NapiResult<Weak<T>> {
+        Weak::new(self.get()?)
+    }
+}
+
+/// A weak reference to a js value, which does not keep the value from being garbage-collected.
+///
+/// Like `Persistent<T>`, it is `Send` and `Sync`, but can only be dereferenced on the thread of
+/// its env.
+#[derive(Debug)]
+pub struct Weak<T: NapiValueT> {
+    reference: Reference,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T: NapiValueT> Weak<T> {
+    /// Create a weak reference to `value`.
+    pub fn new(value: T) -> NapiResult<Weak<T>> {
+        Ok(Weak {
+            reference: Reference::new(value.env(), value.raw(), 0)?,
+            _value: PhantomData,
+        })
+    }
+
+    /// `NapiEnv` of the referenced value
+    pub fn env(&self) -> NapiEnv {
+        self.reference.env
+    }
+
+    /// The referenced value, None if it has been garbage-collected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it is not called on the thread of the env.
+    pub fn get(&self) -> NapiResult<Option<T>> {
+        Ok(self
+            .reference
+            .value()?
+            .map(|value| T::from_raw(self.env(), value)))
+    }
+
+    /// Create a strong reference to the value, None if it has been garbage-collected.
+    pub fn upgrade(&self) -> NapiResult<Option<Persistent<T>>> {
+        self.get()?.map(Persistent::new).transpose()
+    }
+}
@@ -1 +1,316 @@
-// async runtime
+//! Async runtime
+//!
+//! Rust futures are spawned on a runtime, and the output of a future settles a js promise on the
+//! js thread through a threadsafe function.
+//!
+//! The default runtime is chosen by cargo features:
+//!
+//! * `rt` - a built-in minimal executor polling the futures on a dedicated thread
+//! * `rt-tokio` - a tokio multi-thread runtime
+//! * `rt-tokio-current-thread` - a tokio current-thread runtime driven on a dedicated thread
+//!
+//! Another runtime can be plugged in by `set_runtime` before the first future is spawned.
+
+use crate::prelude::*;
+use std::{
+    future::Future,
+    panic::{catch_unwind, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        mpsc::{channel, Sender},
+        Arc, Mutex, OnceLock, RwLock,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+/// The future accepted by a runtime.
+pub type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Runtime the futures are spawned on.
+pub trait Runtime: Send + Sync + 'static {
+    /// Run the future to completion in the background.
+    fn spawn(&self, future: BoxFuture);
+}
+
+static RUNTIME: OnceLock<Box<dyn Runtime>> = OnceLock::new();
+
+/// Use `runtime` for the futures spawned by nodex, it must be called before the first future is
+/// spawned, or napi_generic_failure is returned.
+pub fn set_runtime(runtime: impl Runtime) -> NapiResult<()> {
+    RUNTIME
+        .set(Box::new(runtime))
+        .map_err(|_| NapiStatus::GenericFailure)
+}
+
+fn runtime() -> &'static dyn Runtime {
+    RUNTIME.get_or_init(default_runtime).as_ref()
+}
+
+#[cfg(feature = "rt-tokio")]
+fn default_runtime() -> Box<dyn Runtime> {
+    Box::new(TokioRuntime::multi_thread().expect("nodex: failed to build the tokio runtime"))
+}
+
+#[cfg(all(feature = "rt-tokio-current-thread", not(feature = "rt-tokio")))]
+fn default_runtime() -> Box<dyn Runtime> {
+    Box::new(TokioRuntime::current_thread().expect("nodex: failed to build the tokio runtime"))
+}
+
+#[cfg(not(any(feature = "rt-tokio", feature = "rt-tokio-current-thread")))]
+fn default_runtime() -> Box<dyn Runtime> {
+    Box::new(Executor::new())
+}
+
+/// Spawn a future on the runtime, the returned promise is settled on the js thread when the
+/// future completes:
+///
+/// * resolved with the output converted by `ToJs`
+/// * rejected with the exception thrown by the conversion, e.g. an `Err` of `Result<T, E>`
+/// * rejected with an Error if the future panics, or if the runtime drops it before completion
+pub fn spawn<F>(
+    env: NapiEnv,
+    future: F,
+) -> NapiResult<JsPromise<<F::Output as ToJs>::Value, JsValue>>
+where
+    F: Future + Send + 'static,
+    F::Output: ToJs + Send + 'static,
+    <F::Output as ToJs>::Value: Copy + 'static,
+{
+    let promise = JsPromise::new(env)?;
+
+    // NB: the threadsafe function keeps the event loop alive until the future completes, it is
+    // finalized when the env is torn down though, the raw handle is dangling then.
+    let closed = Arc::new(RwLock::new(false));
+    let tsfn: NapiTsfn<Output<F::Output>> = NapiTsfn::new(
+        env,
+        "nodex-future",
+        env.func(|this, ()| this.env().undefined())?,
+        {
+            let closed = closed.clone();
+            move |_| {
+                *closed.write().unwrap() = true;
+                Ok(())
+            }
+        },
+        move |_, output| settle(env, promise, output),
+    )?;
+
+    let completion = Completion {
+        tsfn: Some(tsfn),
+        closed,
+    };
+    runtime().spawn(Box::pin(async move {
+        let output = CatchUnwind(Box::pin(future)).await;
+        completion.complete(output);
+    }));
+
+    Ok(promise)
+}
+
+/// The output of a spawned future, None if the future is dropped before completion.
+type Output<T> = Option<std::thread::Result<T>>;
+
+/// Sends the output of a spawned future to the js thread and releases the threadsafe function,
+/// the promise is rejected when it is dropped without an output.
+struct Completion<T: Send + 'static> {
+    tsfn: Option<NapiTsfn<Output<T>>>,
+    // NB: true once the threadsafe function is finalized
+    closed: Arc<RwLock<bool>>,
+}
+
+impl<T: Send + 'static> Completion<T> {
+    fn complete(mut self, output: std::thread::Result<T>) {
+        self.send(Some(output));
+    }
+
+    fn send(&mut self, output: Output<T>) {
+        if let Some(tsfn) = self.tsfn.take() {
+            // NB: the output is dropped if the env is torn down, the lock keeps the threadsafe
+            // function from being finalized meanwhile
+            let closed = self.closed.read().unwrap();
+            if *closed {
+                return;
+            }
+            if let Err(e) = tsfn.non_blocking(output) {
+                log::warn!("nodex::rt::spawn: {}", e);
+            }
+            if let Err(e) = tsfn.release() {
+                log::warn!("nodex::rt::spawn: {}", e);
+            }
+        }
+    }
+}
+
+impl<T: Send + 'static> Drop for Completion<T> {
+    fn drop(&mut self) {
+        self.send(None);
+    }
+}
+
+/// Settle the promise by the output of a future, called on the js thread.
+fn settle<T>(
+    env: NapiEnv,
+    promise: JsPromise<T::Value, JsValue>,
+    output: Output<T>,
+) -> NapiResult<()>
+where
+    T: ToJs,
+    T::Value: Copy,
+{
+    let value = match output {
+        Some(Ok(output)) => output.to_js(env),
+        Some(Err(panic)) => {
+            env.throw_error(format!(
+                "future panicked: {}",
+                crate::callback::panic_message(&panic)
+            ))?;
+            Err(NapiStatus::PendingException)
+        }
+        None => {
+            env.throw_error("future dropped before completion")?;
+            Err(NapiStatus::PendingException)
+        }
+    };
+
+    match value {
+        Ok(value) => promise.resolve(value),
+        Err(status) => {
+            let reason = match env.get_and_clear_last_exception()? {
+                Some(exception) => exception.value(),
+                None => env.error(format!("{}", status))?.value(),
+            };
+            promise.reject(reason)
+        }
+    }
+}
+
+/// Catch the panic of the inner future.
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = std::thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match catch_unwind(AssertUnwindSafe(|| self.0.as_mut().poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
+
+/// A minimal executor polling the futures on a dedicated thread.
+pub struct Executor {
+    queue: Sender<Arc<Task>>,
+}
+
+struct Task {
+    future: Mutex<Option<BoxFuture>>,
+    queue: Sender<Arc<Task>>,
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        let queue = self.queue.clone();
+        // NB: the executor thread never exits, so the task is always received.
+        let _ = queue.send(self);
+    }
+}
+
+impl Executor {
+    pub fn new() -> Executor {
+        let (queue, tasks) = channel::<Arc<Task>>();
+
+        std::thread::Builder::new()
+            .name("nodex-rt".into())
+            .spawn(move || {
+                for task in tasks {
+                    let mut slot = task.future.lock().unwrap();
+                    // NB: a completed task may be woken up again
+                    if let Some(mut future) = slot.take() {
+                        let waker = Waker::from(task.clone());
+                        let mut cx = Context::from_waker(&waker);
+                        if future.as_mut().poll(&mut cx).is_pending() {
+                            *slot = Some(future);
+                        }
+                    }
+                }
+            })
+            .expect("nodex: failed to spawn the executor thread");
+
+        Executor { queue }
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Executor {
+        Executor::new()
+    }
+}
+
+impl Runtime for Executor {
+    fn spawn(&self, future: BoxFuture) {
+        let task = Arc::new(Task {
+            future: Mutex::new(Some(future)),
+            queue: self.queue.clone(),
+        });
+        task.wake();
+    }
+}
+
+#[cfg(any(feature = "rt-tokio", feature = "rt-tokio-current-thread"))]
+/// Tokio runtime
+pub struct TokioRuntime {
+    handle: tokio::runtime::Handle,
+    // NB: keep the multi-thread runtime alive
+    _runtime: Option<tokio::runtime::Runtime>,
+}
+
+#[cfg(any(feature = "rt-tokio", feature = "rt-tokio-current-thread"))]
+impl TokioRuntime {
+    #[cfg(feature = "rt-tokio")]
+    /// Build a tokio multi-thread runtime.
+    pub fn multi_thread() -> std::io::Result<TokioRuntime> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .thread_name("nodex-tokio")
+            .enable_all()
+            .build()?;
+
+        Ok(TokioRuntime {
+            handle: runtime.handle().clone(),
+            _runtime: Some(runtime),
+        })
+    }
+
+    /// Build a tokio current-thread runtime, which is driven on a dedicated thread.
+    pub fn current_thread() -> std::io::Result<TokioRuntime> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+
+        std::thread::Builder::new()
+            .name("nodex-tokio".into())
+            .spawn(move || runtime.block_on(std::future::pending::<()>()))?;
+
+        Ok(TokioRuntime {
+            handle,
+            _runtime: None,
+        })
+    }
+
+    /// Spawn the futures on an existing tokio runtime.
+    pub fn from_handle(handle: tokio::runtime::Handle) -> TokioRuntime {
+        TokioRuntime {
+            handle,
+            _runtime: None,
+        }
+    }
+}
+
+#[cfg(any(feature = "rt-tokio", feature = "rt-tokio-current-thread"))]
+impl Runtime for TokioRuntime {
+    fn spawn(&self, future: BoxFuture) {
+        self.handle.spawn(future);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::prelude::*;
+use std::marker::PhantomData;
+
+/// A handle scope which hands out values bound to its lifetime `'s`, opened by
+/// `NapiEnv::local_scope`.
+///
+/// The scope is only available inside the closure running in it, so a `Local<'s, T>` can not
+/// outlive the scope, and a nested scope borrows its parent mutably, so values are always
+/// bound to the innermost scope which is active. Use-after-scope is a compile error:
+///
+/// ```compile_fail
+/// # use nodex_api::prelude::*;
+/// fn leak(env: NapiEnv) -> NapiResult<()> {
+///     let mut leaked = None;
+///     env.local_scope(|scope| {
+///         leaked = Some(scope.string("leaked").unwrap());
+///     })?;
+///     Ok(())
+/// }
+/// ```
+///
+/// The inner value is not handed out, copying it out is a compile error as well:
+///
+/// ```compile_fail
+/// # use nodex_api::prelude::*;
+/// fn leak(env: NapiEnv) -> NapiResult<JsString> {
+///     env.local_scope(|scope| *scope.string("leaked").unwrap())
+/// }
+/// ```
+///
+/// A value is moved to the parent scope by `escape`, or out of the local scopes by
+/// `NapiEnv::local_scope_escape`:
+///
+/// ```
+/// # use nodex_api::prelude::*;
+/// fn greet(env: NapiEnv) -> NapiResult<()> {
+///     env.local_scope(|scope| -> NapiResult<()> {
+///         let greeting = scope.escape(|inner| {
+///             let name = inner.string("nodex")?;
+///             inner.string(format!("hello, {}", name.get()?))
+///         })?;
+///         assert_eq!(greeting.get()?, "hello, nodex");
+///         Ok(())
+///     })?
+/// }
+/// ```
+///
+/// NB: the values created by `NapiEnv` are not bound to any scope, `bind` and
+/// `Local::into_inner` convert between both worlds, and are unsafe.
+pub struct LocalScope<'s> {
+    env: NapiEnv,
+    // NB: 's is invariant
+    _scope: PhantomData<fn(&'s ()) -> &'s ()>,
+}
+
+impl<'s> LocalScope<'s> {
+    pub(crate) fn new(env: NapiEnv) -> LocalScope<'s> {
+        LocalScope {
+            env,
+            _scope: PhantomData,
+        }
+    }
+
+    /// `NapiEnv` of this scope
+    pub fn env(&self) -> NapiEnv {
+        self.env
+    }
+
+    /// Bind a value to this scope.
+    ///
+    /// # Safety
+    ///
+    /// The value must be created while this scope is active, or in an outer scope which is
+    /// still open.
+    pub unsafe fn bind<T: NapiValueT>(&self, value: T) -> Local<'s, T> {
+        Local::new(value)
+    }
+
+    /// Create a value in this scope by `create`, which is called while the scope is active.
+    fn create<T: NapiValueT>(
+        &self,
+        create: impl FnOnce(NapiEnv) -> NapiResult<T>,
+    ) -> NapiResult<Local<'s, T>> {
+        Ok(Local::new(create(self.env)?))
+    }
+
+    /// `undefined` in this scope
+    pub fn undefined(&self) -> NapiResult<Local<'s, JsUndefined>> {
+        self.create(|env| env.undefined())
+    }
+
+    /// `null` in this scope
+    pub fn null(&self) -> NapiResult<Local<'s, JsNull>> {
+        self.create(|env| env.null())
+    }
+
+    /// A boolean in this scope
+    pub fn boolean(&self, value: bool) -> NapiResult<Local<'s, JsBoolean>> {
+        self.create(|env| env.boolean(value))
+    }
+
+    /// A number of an i32 in this scope
+    pub fn int32(&self, value: i32) -> NapiResult<Local<'s, JsNumber>> {
+        self.create(|env| env.int32(value))
+    }
+
+    /// A number of an u32 in this scope
+    pub fn uint32(&self, value: u32) -> NapiResult<Local<'s, JsNumber>> {
+        self.create(|env| env.uint32(value))
+    }
+
+    /// A number of an f64 in this scope
+    pub fn double(&self, value: f64) -> NapiResult<Local<'s, JsNumber>> {
+        self.create(|env| env.double(value))
+    }
+
+    /// A string in this scope
+    pub fn string(&self, value: impl AsRef<str>) -> NapiResult<Local<'s, JsString>> {
+        self.create(|env| env.string(value))
+    }
+
+    /// An empty object in this scope
+    pub fn object(&self) -> NapiResult<Local<'s, JsObject>> {
+        self.create(|env| env.object())
+    }
+
+    /// An empty array in this scope
+    pub fn array(&self) -> NapiResult<Local<'s, JsArray>> {
+        self.create(|env| env.array())
+    }
+
+    /// Run `task` in a nested scope, the values created in it are released when it returns.
+    pub fn scope<R>(
+        &mut self,
+        task: impl for<'i> FnOnce(&mut LocalScope<'i>) -> R,
+    ) -> NapiResult<R> {
+        self.env.local_scope(task)
+    }
+
+    /// Run `task` in a nested escapable scope, the value it returns is promoted to this scope.
+    pub fn escape<T: NapiValueT>(
+        &mut self,
+        task: impl for<'i> FnOnce(&mut LocalScope<'i>) -> NapiResult<Local<'i, T>>,
+    ) -> NapiResult<Local<'s, T>> {
+        let mut scope = NapiEscapableHandleScope::open(self.env)?;
+        let escapee = task(&mut LocalScope::new(self.env))?;
+        Ok(Local::new(scope.escape(escapee.value)?))
+    }
+}
+
+/// A js value bound to the lifetime of the `LocalScope` it is created in.
+#[derive(Copy, Clone, Debug)]
+pub struct Local<'s, T: NapiValueT> {
+    value: T,
+    _scope: PhantomData<&'s ()>,
+}
+
+impl<'s, T: NapiValueT> Local<'s, T> {
+    fn new(value: T) -> Local<'s, T> {
+        Local {
+            value,
+            _scope: PhantomData,
+        }
+    }
+
+    /// `NapiEnv` of the value
+    pub fn env(&self) -> NapiEnv {
+        self.value.env()
+    }
+
+    /// The value as a `JsValue`, bound to the same scope.
+    pub fn value(&self) -> Local<'s, JsValue> {
+        Local::new(self.value.value())
+    }
+
+    /// The inner value, which is not bound to the scope anymore.
+    ///
+    /// # Safety
+    ///
+    /// The value must not be used after the scope is closed.
+    pub unsafe fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<'s> Local<'s, JsString> {
+    /// The string as a rust `String`.
+    pub fn get(&self) -> NapiResult<String> {
+        self.value.get()
+    }
+}
+
+impl<'s> Local<'s, JsNumber> {
+    /// The number as a f64.
+    pub fn get(&self) -> NapiResult<f64> {
+        self.value.get_value_double()
+    }
+}
+
+impl<'s> Local<'s, JsBoolean> {
+    /// The boolean as a rust `bool`.
+    pub fn get(&self) -> NapiResult<bool> {
+        self.value.get()
+    }
+}
+
+impl<'s> Local<'s, JsObject> {
+    /// Get the property `key`, bound to the same scope.
+    pub fn get(&self, key: impl AsRef<str>) -> NapiResult<Local<'s, JsValue>> {
+        Ok(Local::new(self.value.get_named_property(key)?))
+    }
+
+    /// Set the property `key` to `value`.
+    pub fn set<V: NapiValueT>(&mut self, key: impl AsRef<str>, value: Local<V>) -> NapiResult<()> {
+        self.value.set_named_property(key, value.value)
+    }
+}
+
+impl<'s> Local<'s, JsArray> {
+    /// The length of the array.
+    pub fn len(&self) -> NapiResult<u32> {
+        self.value.len()
+    }
+
+    /// The array is empty.
+    pub fn is_empty(&self) -> NapiResult<bool> {
+        self.value.is_empty()
+    }
+
+    /// Get the element at `index`, bound to the same scope.
+    pub fn get(&self, index: u32) -> NapiResult<Local<'s, JsValue>> {
+        Ok(Local::new(self.value.get(index)?))
+    }
+
+    /// Set the element at `index` to `value`.
+    pub fn set<V: NapiValueT>(&mut self, index: u32, value: Local<V>) -> NapiResult<()> {
+        self.value.set(index, value.value)
+    }
+}
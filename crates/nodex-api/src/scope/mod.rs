@@ -5,3 +5,6 @@ pub use callback::*;
 
 mod handle;
 pub use handle::*;
+
+mod local;
+pub use local::{Local, LocalScope};
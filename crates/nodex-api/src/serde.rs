@@ -0,0 +1,921 @@
+//! Convert rust values from and to js values with serde, enabled by the `serde` feature.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     path: String,
+//!     cache: Option<Cache>,
+//! }
+//!
+//! let config: Config = nodex::serde::from_js(params.value())?;
+//! let value = nodex::serde::to_js(env, &config)?;
+//! ```
+//!
+//! Errors carry the path to the failing value, e.g. `cache.size: expected number`.
+
+use crate::{api, prelude::*};
+use ::serde::{
+    de::{self, DeserializeOwned, IntoDeserializer},
+    ser::{self, Serialize},
+};
+use std::{fmt, mem::MaybeUninit};
+
+/// Serialize a rust value into a js value.
+pub fn to_js<T: Serialize + ?Sized>(env: NapiEnv, value: &T) -> Result<JsValue, Error> {
+    value.serialize(Serializer::new(env))
+}
+
+/// Deserialize a rust value from a js value.
+pub fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(value))
+}
+
+/// A step of the path from the root value to the failing value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// The serde conversion error, with the path to the failing value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    path: Vec<Segment>,
+    message: String,
+}
+
+impl Error {
+    fn new(message: impl fmt::Display) -> Error {
+        Error {
+            path: vec![],
+            message: message.to_string(),
+        }
+    }
+
+    fn expected(what: &str) -> Error {
+        Error::new(format!("expected {}", what))
+    }
+
+    /// the error happens within `segment` of the current value
+    fn within(mut self, segment: Segment) -> Error {
+        self.path.insert(0, segment);
+        self
+    }
+
+    /// The path to the failing value, like `config.items[2].name`, empty for the root value.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+        for segment in self.path.iter() {
+            match segment {
+                Segment::Key(key) if path.is_empty() => path.push_str(key),
+                Segment::Key(key) => {
+                    path.push('.');
+                    path.push_str(key);
+                }
+                Segment::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        path
+    }
+
+    /// The error message without the path.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path(), self.message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::new(msg)
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::new(msg)
+    }
+}
+
+impl From<NapiStatus> for Error {
+    fn from(status: NapiStatus) -> Error {
+        Error::new(status)
+    }
+}
+
+/// The largest integer a js number can represent exactly, `Number.MAX_SAFE_INTEGER`.
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.;
+
+/// Deserialize rust values from a js value.
+pub struct Deserializer {
+    value: JsValue,
+}
+
+impl Deserializer {
+    pub fn new(value: JsValue) -> Deserializer {
+        Deserializer { value }
+    }
+
+    /// the bytes of an ArrayBuffer, a TypedArray or a Buffer
+    fn bytes(&self) -> Result<Option<Vec<u8>>, Error> {
+        if self.value.is_arraybuffer()? {
+            return Ok(Some(self.value.as_arraybuffer()?.buffer()?.to_vec()));
+        }
+
+        if self.value.is_typedarray()? {
+            return Ok(Some(typedarray_bytes(self.value)?));
+        }
+
+        Ok(None)
+    }
+
+    fn visit_number<'de, V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.kind()? {
+            NapiValuetype::Number => {
+                let number = self.value.as_number()?.get_value_double()?;
+                if number.fract() != 0. || number.abs() > MAX_SAFE_INTEGER {
+                    visitor.visit_f64(number)
+                } else if number < 0. {
+                    visitor.visit_i64(number as i64)
+                } else {
+                    visitor.visit_u64(number as u64)
+                }
+            }
+            NapiValuetype::Bigint => self.visit_bigint(visitor),
+            _ => Err(Error::expected("number")),
+        }
+    }
+
+    #[cfg(feature = "v6")]
+    fn visit_bigint<'de, V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let bigint = self.value.as_bigint()?;
+
+        if let (signed, true) = bigint.get_i64()? {
+            return visitor.visit_i64(signed);
+        }
+        if let (unsigned, true) = bigint.get_u64()? {
+            return visitor.visit_u64(unsigned);
+        }
+        if let Some(signed) = bigint.to_i128()? {
+            return visitor.visit_i128(signed);
+        }
+        if let Some(unsigned) = bigint.to_u128()? {
+            return visitor.visit_u128(unsigned);
+        }
+
+        Err(Error::new("bigint out of range"))
+    }
+
+    #[cfg(not(feature = "v6"))]
+    fn visit_bigint<'de, V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::new("bigint requires napi v6"))
+    }
+
+    fn visit_array<'de, V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let array = self.value.as_array()?;
+        visitor.visit_seq(ArrayAccess {
+            array,
+            index: 0,
+            len: array.len()?,
+        })
+    }
+
+    fn visit_object<'de, V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let object = self.value.as_object()?;
+        let keys = object.get_property_names()?;
+        visitor.visit_map(ObjectAccess {
+            object,
+            keys,
+            index: 0,
+            len: keys.len()?,
+            key: None,
+        })
+    }
+
+    fn is_nullish(&self) -> Result<bool, Error> {
+        Ok(matches!(
+            self.value.kind()?,
+            NapiValuetype::Null | NapiValuetype::Undefined
+        ))
+    }
+}
+
+/// the bytes viewed by a TypedArray or a Buffer
+fn typedarray_bytes(value: JsValue) -> NapiResult<Vec<u8>> {
+    let mut kind = MaybeUninit::uninit();
+    let mut length = MaybeUninit::uninit();
+    let mut data = MaybeUninit::uninit();
+
+    napi_call!(
+        napi_get_typedarray_info,
+        value.env(),
+        value.raw(),
+        kind.as_mut_ptr(),
+        length.as_mut_ptr(),
+        data.as_mut_ptr(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+    )?;
+
+    unsafe {
+        let size = kind.assume_init().element_size();
+        let len = length.assume_init() * size;
+        if len == 0 {
+            return Ok(vec![]);
+        }
+        Ok(std::slice::from_raw_parts(data.assume_init() as *const u8, len).to_vec())
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident),+ $(,)?) => {
+        $(
+            fn $method<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.visit_number(visitor)
+            }
+        )+
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.kind()? {
+            NapiValuetype::Undefined | NapiValuetype::Null => visitor.visit_unit(),
+            NapiValuetype::Boolean => visitor.visit_bool(self.value.as_boolean()?.get()?),
+            NapiValuetype::Number | NapiValuetype::Bigint => self.visit_number(visitor),
+            NapiValuetype::String => visitor.visit_string(self.value.as_string()?.get()?),
+            NapiValuetype::Object => {
+                if self.value.is_array()? {
+                    self.visit_array(visitor)
+                } else if let Some(bytes) = self.bytes()? {
+                    visitor.visit_byte_buf(bytes)
+                } else {
+                    self.visit_object(visitor)
+                }
+            }
+            kind => Err(Error::new(format!("unsupported js value: {:?}", kind))),
+        }
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.kind()? {
+            NapiValuetype::Boolean => visitor.visit_bool(self.value.as_boolean()?.get()?),
+            _ => Err(Error::expected("boolean")),
+        }
+    }
+
+    deserialize_number!(
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_u128,
+        deserialize_f32,
+        deserialize_f64,
+    );
+
+    fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.kind()? {
+            NapiValuetype::String => visitor.visit_string(self.value.as_string()?.get()?),
+            _ => Err(Error::expected("string")),
+        }
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.value.is_array()? {
+            return self.visit_array(visitor);
+        }
+
+        match self.bytes()? {
+            Some(bytes) => visitor.visit_byte_buf(bytes),
+            None => Err(Error::expected("buffer")),
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_nullish()? {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_nullish()? {
+            visitor.visit_unit()
+        } else {
+            Err(Error::expected("null"))
+        }
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// buffers are sequences of bytes, e.g. `Vec<u8>`
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.value.is_array()? {
+            return self.visit_array(visitor);
+        }
+
+        match self.bytes()? {
+            Some(bytes) => visitor.visit_seq(de::value::SeqDeserializer::new(bytes.into_iter())),
+            None => Err(Error::expected("array")),
+        }
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.value.kind()? == NapiValuetype::Object && !self.value.is_array()? {
+            self.visit_object(visitor)
+        } else {
+            Err(Error::expected("object"))
+        }
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    /// unit variants are strings, other variants are objects with a single key
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value.kind()? {
+            NapiValuetype::String => {
+                let variant = self.value.as_string()?.get()?;
+                visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant))
+            }
+            NapiValuetype::Object => {
+                let object = self.value.as_object()?;
+                let keys = object.get_property_names()?;
+                if keys.len()? != 1 {
+                    return Err(Error::expected("object with a single key"));
+                }
+                let key = keys.get(0)?;
+                visitor.visit_enum(VariantAccess {
+                    variant: key.as_string()?.get()?,
+                    value: object.get_property(key)?,
+                })
+            }
+            _ => Err(Error::expected("string or object")),
+        }
+    }
+
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+struct ArrayAccess {
+    array: JsArray,
+    index: u32,
+    len: u32,
+}
+
+impl<'de> de::SeqAccess<'de> for ArrayAccess {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.index >= self.len {
+            return Ok(None);
+        }
+
+        let index = self.index;
+        self.index += 1;
+
+        let value = self.array.get(index)?;
+        seed.deserialize(Deserializer::new(value))
+            .map(Some)
+            .map_err(|e| e.within(Segment::Index(index as usize)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.len - self.index) as usize)
+    }
+}
+
+struct ObjectAccess {
+    object: JsObject,
+    keys: JsArray,
+    index: u32,
+    len: u32,
+    /// the key of the next value
+    key: Option<(JsValue, String)>,
+}
+
+impl<'de> de::MapAccess<'de> for ObjectAccess {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.index >= self.len {
+            return Ok(None);
+        }
+
+        let key = self.keys.get(self.index)?;
+        let name = key.coerce_to_string()?.get()?;
+        self.index += 1;
+        self.key.replace((key, name.clone()));
+
+        seed.deserialize(IntoDeserializer::<Error>::into_deserializer(name))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, name) = self
+            .key
+            .take()
+            .ok_or_else(|| Error::new("value is missing its key"))?;
+        let value = self.object.get_property(key)?;
+        seed.deserialize(Deserializer::new(value))
+            .map_err(|e| e.within(Segment::Key(name)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.len - self.index) as usize)
+    }
+}
+
+struct VariantAccess {
+    variant: String,
+    value: JsValue,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess {
+    type Error = Error;
+    type Variant = VariantAccess;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantAccess), Error> {
+        let variant = IntoDeserializer::<Error>::into_deserializer(self.variant.clone());
+        Ok((seed.deserialize(variant)?, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(Deserializer::new(self.value))
+            .map_err(|e| e.within(Segment::Key(self.variant)))
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(Deserializer::new(self.value), visitor)
+            .map_err(|e| e.within(Segment::Key(self.variant)))
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(Deserializer::new(self.value), visitor)
+            .map_err(|e| e.within(Segment::Key(self.variant)))
+    }
+}
+
+/// Serialize rust values into js values.
+#[derive(Copy, Clone)]
+pub struct Serializer {
+    env: NapiEnv,
+}
+
+impl Serializer {
+    pub fn new(env: NapiEnv) -> Serializer {
+        Serializer { env }
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = JsValue;
+    type Error = Error;
+
+    type SerializeSeq = ArraySerializer;
+    type SerializeTuple = ArraySerializer;
+    type SerializeTupleStruct = ArraySerializer;
+    type SerializeTupleVariant = VariantSerializer<ArraySerializer>;
+    type SerializeMap = ObjectSerializer;
+    type SerializeStruct = ObjectSerializer;
+    type SerializeStructVariant = VariantSerializer<ObjectSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<JsValue, Error> {
+        Ok(self.env.boolean(v)?.value())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<JsValue, Error> {
+        Ok(self.env.int32(v as i32)?.value())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<JsValue, Error> {
+        Ok(self.env.int32(v as i32)?.value())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<JsValue, Error> {
+        Ok(self.env.int32(v)?.value())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<JsValue, Error> {
+        Ok(self.env.int64(v)?.value())
+    }
+
+    #[cfg(feature = "v6")]
+    fn serialize_i128(self, v: i128) -> Result<JsValue, Error> {
+        Ok(JsBigInt::from_i128(self.env, v)?.value())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<JsValue, Error> {
+        Ok(self.env.uint32(v as u32)?.value())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<JsValue, Error> {
+        Ok(self.env.uint32(v as u32)?.value())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<JsValue, Error> {
+        Ok(self.env.uint32(v)?.value())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<JsValue, Error> {
+        Ok(self.env.double(v as f64)?.value())
+    }
+
+    #[cfg(feature = "v6")]
+    fn serialize_u128(self, v: u128) -> Result<JsValue, Error> {
+        Ok(JsBigInt::from_u128(self.env, v)?.value())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<JsValue, Error> {
+        Ok(self.env.double(v as f64)?.value())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<JsValue, Error> {
+        Ok(self.env.double(v)?.value())
+    }
+
+    fn serialize_char(self, v: char) -> Result<JsValue, Error> {
+        Ok(self.env.string(v.encode_utf8(&mut [0; 4]))?.value())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<JsValue, Error> {
+        Ok(self.env.string(v)?.value())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<JsValue, Error> {
+        Ok(self.env.arraybuffer(v)?.value())
+    }
+
+    fn serialize_none(self) -> Result<JsValue, Error> {
+        Ok(self.env.null()?.value())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<JsValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<JsValue, Error> {
+        Ok(self.env.null()?.value())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<JsValue, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<JsValue, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<JsValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<JsValue, Error> {
+        let mut object = self.env.object()?;
+        let value = value
+            .serialize(self)
+            .map_err(|e| e.within(Segment::Key(variant.to_string())))?;
+        object.set(variant, value)?;
+        Ok(object.value())
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ArraySerializer, Error> {
+        Ok(ArraySerializer {
+            env: self.env,
+            array: JsArray::new(self.env, len.unwrap_or(0))?,
+            index: 0,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ArraySerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ArraySerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<ArraySerializer>, Error> {
+        Ok(VariantSerializer {
+            env: self.env,
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<ObjectSerializer, Error> {
+        Ok(ObjectSerializer {
+            env: self.env,
+            object: self.env.object()?,
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<ObjectSerializer, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<ObjectSerializer>, Error> {
+        Ok(VariantSerializer {
+            env: self.env,
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+pub struct ArraySerializer {
+    env: NapiEnv,
+    array: JsArray,
+    index: u32,
+}
+
+impl ser::SerializeSeq for ArraySerializer {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let index = self.index;
+        let value = to_js(self.env, value).map_err(|e| e.within(Segment::Index(index as usize)))?;
+        self.array.set(index, value)?;
+        self.index += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        Ok(self.array.value())
+    }
+}
+
+impl ser::SerializeTuple for ArraySerializer {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ArraySerializer {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct ObjectSerializer {
+    env: NapiEnv,
+    object: JsObject,
+    /// the key of the next value
+    key: Option<(JsValue, String)>,
+}
+
+impl ser::SerializeMap for ObjectSerializer {
+    type Ok = JsValue;
+    type Error = Error;
+
+    /// keys are serialized as strings or numbers
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = to_js(self.env, key)?;
+        match key.kind()? {
+            NapiValuetype::String | NapiValuetype::Number => {
+                let name = key.coerce_to_string()?.get()?;
+                self.key.replace((key, name));
+                Ok(())
+            }
+            _ => Err(Error::new("key must be a string or a number")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let (key, name) = self
+            .key
+            .take()
+            .ok_or_else(|| Error::new("value is missing its key"))?;
+        let value = to_js(self.env, value).map_err(|e| e.within(Segment::Key(name)))?;
+        self.object.set_property(key, value)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        Ok(self.object.value())
+    }
+}
+
+impl ser::SerializeStruct for ObjectSerializer {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let value = to_js(self.env, value).map_err(|e| e.within(Segment::Key(key.to_string())))?;
+        self.object.set(key, value)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        Ok(self.object.value())
+    }
+}
+
+/// Variants with data are serialized as objects with a single key.
+pub struct VariantSerializer<S> {
+    env: NapiEnv,
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> VariantSerializer<S> {
+    fn end(self, value: Result<JsValue, Error>) -> Result<JsValue, Error> {
+        let mut object = self.env.object()?;
+        object.set(self.variant, value?)?;
+        Ok(object.value())
+    }
+
+    fn within<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        result.map_err(|e| e.within(Segment::Key(self.variant.to_string())))
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<ArraySerializer> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let result = ser::SerializeSeq::serialize_element(&mut self.inner, value);
+        self.within(result)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        let value = Ok(self.inner.array.value());
+        VariantSerializer::end(self, value)
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<ObjectSerializer> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let result = ser::SerializeStruct::serialize_field(&mut self.inner, key, value);
+        self.within(result)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        let value = Ok(self.inner.object.value());
+        VariantSerializer::end(self, value)
+    }
+}
@@ -0,0 +1,125 @@
+//! Typed states of an env
+//!
+//! napi keeps a single instance-data pointer per env, so two plugins composed into one addon
+//! would overwrite each other's data. The pointer is taken by a `Registry` instead, which keeps
+//! one state per rust type, and runs the finalizer of each state when the env is torn down, in
+//! the reverse order of insertion.
+//!
+//! Every env has its own instance data, so the main thread and each worker thread loading the
+//! addon get their own registry. The states are `Rc`s, which can not be sent to other threads.
+
+use crate::{api, prelude::*};
+use std::{
+    any::{Any, TypeId},
+    cell::{RefCell, UnsafeCell},
+    rc::Rc,
+};
+
+/// Finalizer of a state, called with the state when the env is torn down.
+pub(crate) type Finalizer = Box<dyn FnOnce(NapiEnv, &dyn Any) -> NapiResult<()>>;
+
+/// Data set by `NapiEnv::set_instance_data`, which is handed out as `&mut T`.
+pub(crate) struct InstanceData<T>(pub(crate) UnsafeCell<Option<T>>);
+
+struct Entry {
+    id: TypeId,
+    state: Rc<dyn Any>,
+    finalizer: Option<Finalizer>,
+}
+
+/// The states of an env, kept in its instance data.
+#[derive(Default)]
+pub(crate) struct Registry {
+    // NB: a few states per env, so a vec keeps the insertion order for the finalizers
+    entries: RefCell<Vec<Entry>>,
+}
+
+impl Registry {
+    /// The registry of `env`, it is installed on first use.
+    ///
+    /// NB: the registry lives until the env is torn down, the reference is not handed out.
+    pub(crate) fn of(env: NapiEnv) -> NapiResult<&'static Registry> {
+        let data = napi_call!(=napi_get_instance_data, env) as *const Registry;
+        if !data.is_null() {
+            return Ok(unsafe { &*data });
+        }
+
+        let registry = Box::into_raw(Box::<Registry>::default());
+        let status = unsafe {
+            api::napi_set_instance_data(
+                env,
+                registry as DataPointer,
+                Some(finalize),
+                std::ptr::null_mut(),
+            )
+        };
+        if status.err() {
+            unsafe {
+                let _ = Box::from_raw(registry);
+            }
+            return Err(status);
+        }
+        Ok(unsafe { &*registry })
+    }
+
+    /// The state of type `T`.
+    pub(crate) fn get<T: 'static>(&self) -> Option<Rc<T>> {
+        let entries = self.entries.borrow();
+        let entry = entries.iter().find(|entry| entry.id == TypeId::of::<T>())?;
+        entry.state.clone().downcast::<T>().ok()
+    }
+
+    /// Insert the state of type `T`, the replaced state is returned without being finalized.
+    pub(crate) fn insert<T: 'static>(
+        &self,
+        state: Rc<T>,
+        finalizer: Option<Finalizer>,
+    ) -> Option<Rc<T>> {
+        let replaced = self.remove::<T>();
+        self.entries.borrow_mut().push(Entry {
+            id: TypeId::of::<T>(),
+            state,
+            finalizer,
+        });
+        replaced
+    }
+
+    /// Remove the state of type `T` and call its finalizer.
+    pub(crate) fn finalize<T: 'static>(&self, env: NapiEnv) {
+        let entry = {
+            let mut entries = self.entries.borrow_mut();
+            match entries
+                .iter()
+                .position(|entry| entry.id == TypeId::of::<T>())
+            {
+                Some(index) => entries.remove(index),
+                None => return,
+            }
+        };
+        // NB: the registry is not borrowed, the finalizer may look up the states
+        if let Some(finalizer) = entry.finalizer {
+            crate::callback::guard("NapiEnv::state finalizer", || finalizer(env, &*entry.state));
+        }
+    }
+
+    /// Remove the state of type `T` without finalizing it.
+    pub(crate) fn remove<T: 'static>(&self) -> Option<Rc<T>> {
+        let mut entries = self.entries.borrow_mut();
+        let index = entries
+            .iter()
+            .position(|entry| entry.id == TypeId::of::<T>())?;
+        entries.remove(index).state.downcast::<T>().ok()
+    }
+}
+
+unsafe extern "C" fn finalize(env: NapiEnv, data: DataPointer, _: DataPointer) {
+    let registry: Box<Registry> = Box::from_raw(data as _);
+    // NB: the entries are taken out first, so a finalizer looking up the states does not
+    // borrow the registry while it is finalized
+    let entries = registry.entries.take();
+    for entry in entries.into_iter().rev() {
+        if let Some(finalizer) = entry.finalizer {
+            crate::callback::guard("NapiEnv::state finalizer", || finalizer(env, &*entry.state));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Type tags of wrapped native instances and externals
+//!
+//! `NapiValueT::wrap` and `JsExternal::new` tag the js value with the `NapiTypeTag` of the rust
+//! type, and `unwrap`, `remove_wrap` and `JsExternal::get` check it before the native pointer is
+//! cast, so asking for another type returns `TypeTagError::Mismatch` instead of reading the
+//! native value as a wrong type.
+//!
+//! The tag of a type is derived from its `std::any::type_name` and a salt of the addon, which is
+//! the address of a static in the copy of nodex linked into the addon. A type name is not unique,
+//! e.g. two versions of one crate, loaded by two addons of a process, have types of the same
+//! name, so the salt keeps the tags of an addon from matching the values of other addons. The
+//! tags are not stable across processes.
+//!
+//! With the `v8` feature the tags are attached by `napi_type_tag_object`. Before napi 8, the tag
+//! of an object is kept in a non-enumerable, read-only and non-configurable property keyed by a
+//! symbol which is created per env and never handed out to js. It is not bulletproof, as
+//! `Object.getOwnPropertySymbols()` reveals the symbol, but it can not be removed or changed.
+//! An external can not carry properties, so the tag is kept next to the native value instead,
+//! which does not detect externals created by other addons.
+
+use crate::{api, prelude::*};
+
+/// Error of accessing the native value of a tagged js value.
+#[derive(thiserror::Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TypeTagError {
+    /// The napi call failed.
+    #[error(transparent)]
+    Napi(#[from] NapiStatus),
+    /// The js value is not tagged with the type tag of `expected`.
+    #[error("type tag mismatch: expected {expected}")]
+    Mismatch { expected: &'static str },
+}
+
+impl TypeTagError {
+    pub(crate) fn mismatch<T: ?Sized>() -> TypeTagError {
+        TypeTagError::Mismatch {
+            expected: std::any::type_name::<T>(),
+        }
+    }
+}
+
+impl From<TypeTagError> for NapiStatus {
+    fn from(e: TypeTagError) -> NapiStatus {
+        match e {
+            TypeTagError::Napi(status) => status,
+            TypeTagError::Mismatch { .. } => NapiStatus::InvalidArg,
+        }
+    }
+}
+
+impl NapiTypeTag {
+    /// The type tag of a rust type in this addon, derived from the salt of the addon and the
+    /// type name by two FNV-1a hashes.
+    pub fn of<T: ?Sized>() -> NapiTypeTag {
+        fn fnv1a(basis: u64, bytes: &[u8]) -> u64 {
+            bytes.iter().fold(basis, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+            })
+        }
+
+        // NB: each addon links its own copy of this static
+        static SALT: u8 = 0;
+        let salt = (&SALT as *const u8 as usize).to_le_bytes();
+        let name = std::any::type_name::<T>().as_bytes();
+        NapiTypeTag {
+            lower: fnv1a(fnv1a(0xcbf29ce484222325, &salt), name),
+            upper: fnv1a(fnv1a(0x84222325cbf29ce4, &salt), name),
+        }
+    }
+}
+
+impl PartialEq for NapiTypeTag {
+    fn eq(&self, other: &NapiTypeTag) -> bool {
+        self.lower == other.lower && self.upper == other.upper
+    }
+}
+
+impl Eq for NapiTypeTag {}
+
+/// Tag `object` with `tag`, napi_invalid_arg is returned if it is tagged with another tag.
+pub(crate) fn tag_object(object: JsValue, tag: &NapiTypeTag) -> NapiResult<()> {
+    if check_object(object, tag)? {
+        return Ok(());
+    }
+
+    #[cfg(feature = "v8")]
+    napi_call!(napi_type_tag_object, object.env(), object.raw(), tag);
+
+    #[cfg(not(feature = "v8"))]
+    hidden::tag(object, tag)?;
+
+    Ok(())
+}
+
+/// Check if `object` is tagged with `tag`.
+pub(crate) fn check_object(object: JsValue, tag: &NapiTypeTag) -> NapiResult<bool> {
+    #[cfg(feature = "v8")]
+    return Ok(napi_call!(
+        =napi_check_object_type_tag,
+        object.env(),
+        object.raw(),
+        tag,
+    ));
+
+    #[cfg(not(feature = "v8"))]
+    return hidden::check(object, tag);
+}
+
+/// The tags kept in a property keyed by a hidden symbol, before napi 8.
+#[cfg(not(feature = "v8"))]
+mod hidden {
+    use super::*;
+    use std::{cell::RefCell, collections::HashMap};
+
+    thread_local! {
+        /// the hidden symbol of each env on this thread
+        static SYMBOLS: RefCell<HashMap<usize, NapiRef>> = RefCell::new(HashMap::new());
+    }
+
+    fn symbol(env: NapiEnv) -> NapiResult<JsSymbol> {
+        let key = env.raw() as usize;
+        // NB: a NapiRef deletes the reference when dropped, so it is never cloned out
+        if let Some(symbol) = SYMBOLS.with(|symbols| {
+            symbols
+                .borrow()
+                .get(&key)
+                .map(|symbol| symbol.deref::<JsSymbol>())
+        }) {
+            return symbol;
+        }
+
+        let symbol = env.symbol_description(env.string("nodex.type_tag")?)?;
+        let reference = NapiRef::new(symbol, 1)?;
+        // NB: forget the symbol once the env is torn down, the pointer of the env may be reused
+        #[cfg(feature = "v3")]
+        env.add_cleanup_hook(move || {
+            SYMBOLS.with(|symbols| symbols.borrow_mut().remove(&key));
+            Ok(())
+        })?;
+        SYMBOLS.with(|symbols| symbols.borrow_mut().insert(key, reference));
+        Ok(symbol)
+    }
+
+    fn has(object: JsObject, symbol: JsSymbol) -> NapiResult<bool> {
+        Ok(napi_call!(
+            =napi_has_own_property,
+            object.env(),
+            object.raw(),
+            symbol.raw(),
+        ))
+    }
+
+    fn encode(tag: &NapiTypeTag) -> String {
+        format!("{:016x}{:016x}", tag.upper, tag.lower)
+    }
+
+    pub(super) fn tag(object: JsValue, tag: &NapiTypeTag) -> NapiResult<()> {
+        let env = object.env();
+        // NB: functions are objects as well
+        let object = unsafe { object.cast::<JsObject>() };
+        let symbol = symbol(env)?;
+        if has(object, symbol)? {
+            return Err(NapiStatus::InvalidArg);
+        }
+        object.define_properties([DescriptorValueBuilder::new()
+            .with_name(symbol)
+            .with_value(env.string(encode(tag))?)
+            .with_attribute(NapiPropertyAttributes::Default)
+            .build()?])
+    }
+
+    pub(super) fn check(object: JsValue, tag: &NapiTypeTag) -> NapiResult<bool> {
+        let env = object.env();
+        let object = unsafe { object.cast::<JsObject>() };
+        let symbol = symbol(env)?;
+        if !has(object, symbol)? {
+            return Ok(false);
+        }
+        let value = object.get_property(symbol)?;
+        Ok(value.kind()? == NapiValuetype::String && value.as_string()?.get()? == encode(tag))
+    }
+}
@@ -1,5 +1,12 @@
-use crate::prelude::*;
-use std::marker::PhantomData;
+use crate::{api, callback::Live, prelude::*};
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex, RwLock},
+    task::{Context, Poll, Waker},
+    thread::ThreadId,
+};
 
 #[derive(Copy, Clone, Debug)]
 pub struct NapiThreadsafeFunction<Data, const N: usize>(
@@ -39,6 +46,21 @@ impl<Data, const N: usize> NapiThreadsafeFunction<Data, N> {
     where
         R: NapiValueT,
     {
+        Self::with_queue_size(env, name, func, N, finalizer, callback)
+    }
+
+    /// Create a napi_threadsafe_function whose queue size is decided at runtime, `N` is ignored.
+    ///
+    /// max_queue_size: the maximum size of the queue, 0 for no limit.
+    #[allow(clippy::type_complexity)]
+    pub fn with_queue_size<R: NapiValueT>(
+        env: NapiEnv,
+        name: impl AsRef<str>,
+        func: Function<R>,
+        max_queue_size: usize,
+        finalizer: impl FnOnce(NapiEnv) -> NapiResult<()>,
+        callback: impl FnMut(Function<R>, Data) -> NapiResult<()>,
+    ) -> NapiResult<NapiThreadsafeFunction<Data, N>> {
         unsafe extern "C" fn finalizer_trampoline(
             env: NapiEnv,
             finalizer: DataPointer,
@@ -47,9 +69,7 @@ impl<Data, const N: usize> NapiThreadsafeFunction<Data, N> {
             let finalizer: Box<Box<dyn FnOnce(NapiEnv) -> NapiResult<()>>> =
                 Box::from_raw(finalizer as _);
 
-            if let Err(err) = finalizer(env) {
-                log::error!("NapiThreadsafeFunction::finalizer(): {}", err);
-            }
+            crate::callback::guard("NapiThreadsafeFunction::finalizer()", || finalizer(env));
         }
 
         unsafe extern "C" fn call_js_trampoline<R: NapiValueT, Data>(
@@ -58,49 +78,61 @@ impl<Data, const N: usize> NapiThreadsafeFunction<Data, N> {
             context: DataPointer,
             data: DataPointer,
         ) {
+            let data: Box<Data> = Box::from_raw(data as _);
+            // NB: the queued data is dropped without a call when the env is torn down
+            if env.raw().is_null() {
+                return;
+            }
             let context: &mut Box<dyn FnMut(Function<R>, Data) -> NapiResult<()>> =
                 std::mem::transmute(&mut *(context as *mut _));
-            let data: Box<Data> = Box::from_raw(data as _);
 
-            if let Err(e) = context(Function::<R>::from_raw(env, cb), *data) {
-                log::error!("NapiThreadsafeFunction::call_js_trampoline: {}", e);
-            }
+            crate::callback::guard("NapiThreadsafeFunction::call_js_trampoline", || {
+                context(Function::<R>::from_raw(env, cb), *data)
+            });
         }
 
+        let name = env.string(name.as_ref())?;
         let context: Box<Box<dyn FnMut(Function<R>, Data) -> NapiResult<()>>> =
             Box::new(Box::new(callback));
-        // NB: leak here
+        // NB: the context is freed by the finalizer
         let context = Box::into_raw(context);
+        let live = Live::new();
         let finalizer: Box<Box<dyn FnOnce(NapiEnv) -> NapiResult<()>>> =
             Box::new(Box::new(move |env| -> NapiResult<()> {
                 unsafe {
-                    Box::from_raw(context);
+                    drop(Box::from_raw(context));
                 }
+                drop(live);
                 finalizer(env)
             }));
+        let finalizer = Box::into_raw(finalizer);
 
-        let tsfn = napi_call!(
-            =napi_create_threadsafe_function,
+        let (status, tsfn) = napi_call!(
+            ?napi_create_threadsafe_function,
             env,
             func.raw(),
             std::ptr::null_mut(),
-            env.string(name.as_ref())?.raw(),
-            N,
+            name.raw(),
+            max_queue_size,
             1,
-            Box::into_raw(finalizer) as _,
+            finalizer as _,
             Some(finalizer_trampoline),
             context as _,
             Some(call_js_trampoline::<R, Data>),
         );
 
+        if status.err() {
+            // NB: the finalizer is never called, free the closures here
+            unsafe {
+                drop(Box::from_raw(context));
+                drop(Box::from_raw(finalizer));
+            }
+            return Err(status);
+        }
+
         Ok(NapiThreadsafeFunction(env, tsfn, PhantomData))
     }
 
-    // pub fn context<C>(&self) -> NapiResult<&mut C> {
-    //     let context = napi_call!(=napi_get_threadsafe_function_context, self.raw());
-    //     unsafe { Ok(std::mem::transmute(context)) }
-    // }
-
     /// This API should not be called with napi_tsfn_blocking from a JavaScript thread, because,
     /// if the queue is full, it may cause the JavaScript thread to deadlock.
     ///
@@ -110,12 +142,16 @@ impl<Data, const N: usize> NapiThreadsafeFunction<Data, N> {
     ///
     /// This API may be called from any thread which makes use of func.
     pub fn call(&self, data: Data, mode: NapiThreadsafeFunctionCallMode) -> NapiResult<()> {
-        napi_call!(
-            napi_call_threadsafe_function,
-            self.raw(),
-            Box::into_raw(Box::new(data)) as _,
-            mode,
-        )
+        let data = Box::into_raw(Box::new(data));
+        let status = unsafe { api::napi_call_threadsafe_function(self.raw(), data as _, mode) };
+        if status.err() {
+            // NB: the data is not queued, drop it here
+            unsafe {
+                drop(Box::from_raw(data));
+            }
+            return Err(status);
+        }
+        Ok(())
     }
 
     #[inline]
@@ -186,3 +222,276 @@ impl<Data, const N: usize> NapiThreadsafeFunction<Data, N> {
 }
 
 pub type NapiTsfn<Data> = NapiThreadsafeFunction<Data, 0>;
+
+/// The error of a call through a `ThreadsafeFunction`.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum TsfnError {
+    /// The call failed in napi, e.g. napi_closing once the env is torn down, or the returned
+    /// value can not be converted.
+    #[error(transparent)]
+    Napi(#[from] NapiStatus),
+    /// The js function threw, the exception is converted to a string.
+    #[error("js exception: {0}")]
+    Thrown(String),
+}
+
+/// A js function which can be called from any thread, the arguments are converted by
+/// `ToJsArgs` and the returned value is converted by `FromJs` on the js thread.
+///
+/// Each handle holds a thread count of the underlying napi_threadsafe_function, which is
+/// acquired when the handle is cloned and released when the handle is dropped. The function is
+/// finalized once all handles are dropped, or when the env is torn down, every call fails with
+/// napi_closing after that.
+///
+/// ```ignore
+/// fn ask(env: NapiEnv, callback: JsFunction) -> NapiResult<JsPromise<JsNumber, JsValue>> {
+///     let add = ThreadsafeFunction::<(u32, u32), u32>::new(callback, 16)?;
+///     env.spawn_future(async move {
+///         let sum = add.call_with_return((1, 2)).await?;
+///         add.call_with_return((sum, 3)).await
+///     })
+/// }
+/// ```
+pub struct ThreadsafeFunction<Args, Ret> {
+    tsfn: NapiTsfn<Call<Args, Ret>>,
+    // NB: true once the function is finalized, the raw handle is dangling then
+    closed: Arc<RwLock<bool>>,
+    // NB: the thread count is not held if acquiring failed
+    acquired: bool,
+    thread: ThreadId,
+}
+
+impl<Args, Ret> ThreadsafeFunction<Args, Ret>
+where
+    Args: ToJsArgs + Send + 'static,
+    Ret: FromJs + Send + 'static,
+{
+    /// Create a threadsafe function calling `func`, at most `max_queue_size` calls are queued,
+    /// 0 for no limit.
+    pub fn new<R: NapiValueT>(func: Function<R>, max_queue_size: usize) -> NapiResult<Self> {
+        let env = func.env();
+        let closed = Arc::new(RwLock::new(false));
+
+        let tsfn = NapiTsfn::with_queue_size(
+            env,
+            "nodex-tsfn",
+            func,
+            max_queue_size,
+            {
+                let closed = closed.clone();
+                move |_| {
+                    *closed.write().unwrap() = true;
+                    Ok(())
+                }
+            },
+            |func, call: Call<Args, Ret>| call.run(func),
+        )?;
+
+        Ok(ThreadsafeFunction {
+            tsfn,
+            closed,
+            acquired: true,
+            thread: std::thread::current().id(),
+        })
+    }
+
+    /// Call the js function and ignore the returned value, an exception thrown by it is
+    /// reported as an uncaught exception.
+    pub fn call(&self, args: Args, mode: NapiThreadsafeFunctionCallMode) -> NapiResult<()> {
+        self.send(Call { args, reply: None }, mode)
+    }
+
+    /// Call the js function, the returned future resolves to the value returned by it, or to
+    /// the exception thrown by it.
+    ///
+    /// The call is queued right away, even if the future is not polled. It waits for a free
+    /// slot of the queue, except on the js thread, where the future resolves to napi_queue_full
+    /// instead to avoid a deadlock. The future must not be blocked on in the js thread.
+    pub fn call_with_return(&self, args: Args) -> TsfnFuture<Ret> {
+        let slot = Arc::new(Mutex::new(Slot {
+            result: None,
+            waker: None,
+        }));
+        let reply = Reply(Some(slot.clone()));
+
+        let mode = if std::thread::current().id() == self.thread {
+            NapiThreadsafeFunctionCallMode::Nonblocking
+        } else {
+            NapiThreadsafeFunctionCallMode::Blocking
+        };
+        if let Err(status) = self.send(
+            Call {
+                args,
+                reply: Some(reply),
+            },
+            mode,
+        ) {
+            slot.lock().unwrap().result = Some(Err(status.into()));
+        }
+
+        TsfnFuture(slot)
+    }
+
+    fn send(&self, call: Call<Args, Ret>, mode: NapiThreadsafeFunctionCallMode) -> NapiResult<()> {
+        let closed = self.closed.read().unwrap();
+        if *closed {
+            return Err(NapiStatus::Closing);
+        }
+        self.tsfn.call(call, mode)
+    }
+}
+
+impl<Args, Ret> ThreadsafeFunction<Args, Ret> {
+    pub fn env(&self) -> NapiEnv {
+        self.tsfn.env()
+    }
+
+    /// Abort the function, the queued calls are dropped and resolve to napi_closing, and all
+    /// handles fail with napi_closing from now on.
+    pub fn abort(mut self) -> NapiResult<()> {
+        let closed = self.closed.read().unwrap();
+        if *closed || !self.acquired {
+            return Err(NapiStatus::Closing);
+        }
+        self.acquired = false;
+        NapiTsfn::<Call<Args, Ret>>::from_raw(self.tsfn.env(), self.tsfn.raw()).abort()
+    }
+
+    /// Keep the event loop alive until the function is finalized, which is the default.
+    ///
+    /// This API may only be called from the main thread.
+    pub fn refer(&self) -> NapiResult<()> {
+        self.tsfn.refer()
+    }
+
+    /// Let the event loop exit before the function is finalized.
+    ///
+    /// This API may only be called from the main thread.
+    pub fn unref(&self) -> NapiResult<()> {
+        self.tsfn.unref()
+    }
+}
+
+impl<Args, Ret> Clone for ThreadsafeFunction<Args, Ret> {
+    fn clone(&self) -> Self {
+        let closed = self.closed.read().unwrap();
+        let acquired = !*closed && self.tsfn.acquire().is_ok();
+        ThreadsafeFunction {
+            tsfn: NapiTsfn::from_raw(self.tsfn.env(), self.tsfn.raw()),
+            closed: self.closed.clone(),
+            acquired,
+            thread: self.thread,
+        }
+    }
+}
+
+impl<Args, Ret> Drop for ThreadsafeFunction<Args, Ret> {
+    fn drop(&mut self) {
+        if !self.acquired {
+            return;
+        }
+        let closed = self.closed.read().unwrap();
+        if *closed {
+            return;
+        }
+        let tsfn = NapiTsfn::<Call<Args, Ret>>::from_raw(self.tsfn.env(), self.tsfn.raw());
+        if let Err(e) = tsfn.release() {
+            log::warn!("nodex::tsfn::ThreadsafeFunction: {}", e);
+        }
+    }
+}
+
+/// A queued call of a `ThreadsafeFunction`.
+struct Call<Args, Ret> {
+    args: Args,
+    reply: Option<Reply<Ret>>,
+}
+
+impl<Args: ToJsArgs, Ret: FromJs> Call<Args, Ret> {
+    /// Call the js function on the js thread.
+    fn run<R: NapiValueT>(self, func: Function<R>) -> NapiResult<()> {
+        let env = func.env();
+        // NB: `this` is undefined, like the default call of a napi_threadsafe_function
+        let this = JsObject::from_raw(env, env.undefined()?.raw());
+        let result = func.call(this, self.args);
+
+        let Some(mut reply) = self.reply else {
+            // NB: the exception is left pending, so it is reported as uncaught
+            return result.map(|_| ());
+        };
+
+        reply.send(match result {
+            Ok(value) => Ret::from_js(value.value()).map_err(TsfnError::from),
+            Err(status) => Err(thrown(env, status)),
+        });
+        Ok(())
+    }
+}
+
+/// Take the exception thrown by the js function.
+fn thrown(env: NapiEnv, status: NapiStatus) -> TsfnError {
+    let exception = match env.get_and_clear_last_exception() {
+        Ok(Some(exception)) => exception.value(),
+        _ => return TsfnError::Napi(status),
+    };
+    match exception
+        .coerce_to_string()
+        .and_then(|message| message.get())
+    {
+        Ok(message) => TsfnError::Thrown(message),
+        Err(_) => {
+            // NB: e.g. a symbol is thrown, which can not be converted
+            let _ = env.get_and_clear_last_exception();
+            TsfnError::Thrown(exception.pretty().to_string())
+        }
+    }
+}
+
+struct Slot<Ret> {
+    result: Option<Result<Ret, TsfnError>>,
+    waker: Option<Waker>,
+}
+
+/// Deliver the result of a call to its future, the future resolves to napi_closing if the call
+/// is dropped without being made.
+struct Reply<Ret>(Option<Arc<Mutex<Slot<Ret>>>>);
+
+impl<Ret> Reply<Ret> {
+    fn send(&mut self, result: Result<Ret, TsfnError>) {
+        let Some(slot) = self.0.take() else {
+            return;
+        };
+        let waker = {
+            let mut slot = slot.lock().unwrap();
+            slot.result = Some(result);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<Ret> Drop for Reply<Ret> {
+    fn drop(&mut self) {
+        self.send(Err(TsfnError::Napi(NapiStatus::Closing)));
+    }
+}
+
+/// The result of `ThreadsafeFunction::call_with_return`.
+pub struct TsfnFuture<Ret>(Arc<Mutex<Slot<Ret>>>);
+
+impl<Ret> Future for TsfnFuture<Ret> {
+    type Output = Result<Ret, TsfnError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.0.lock().unwrap();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -31,6 +31,45 @@ impl JsArrayBuffer {
         Ok(JsArrayBuffer(JsValue::from_raw(env, buffer)))
     }
 
+    /// This API returns a Node-API value corresponding to a JavaScript ArrayBuffer. The
+    /// underlying byte buffer of the ArrayBuffer is the bytes of `data`, which are not copied.
+    /// The ownership of `data` is moved into a finalizer, which drops it when the ArrayBuffer is
+    /// garbage-collected.
+    ///
+    /// js may write to the ArrayBuffer, so the bytes must be owned by `data` alone, e.g. a
+    /// `Vec<u8>` or a `Box<[u8]>`, and `data.as_mut()` must return the same slice until `data` is
+    /// dropped. See JsBuffer::from_external().
+    pub fn from_external<D>(env: NapiEnv, data: D) -> NapiResult<JsArrayBuffer>
+    where
+        D: AsMut<[u8]> + Send + 'static,
+    {
+        let mut data = Box::new(data);
+        let bytes = (*data).as_mut();
+        if bytes.is_empty() {
+            return JsArrayBuffer::new(env, []);
+        }
+        let (ptr, len) = (bytes.as_mut_ptr(), bytes.len());
+
+        let data = Box::into_raw(data);
+        let (status, buffer) = napi_call!(
+            ?napi_create_external_arraybuffer,
+            env,
+            ptr as DataPointer,
+            len,
+            Some(super::buffer::finalize_external::<D>),
+            data as DataPointer,
+        );
+        if status.err() {
+            // NB: the finalizer is not called if it fails
+            unsafe {
+                drop(Box::from_raw(data));
+            }
+            return Err(status);
+        }
+
+        Ok(JsArrayBuffer::from_raw(env, buffer))
+    }
+
     /// This API creates a JavaScript DataView object over an existing ArrayBuffer. DataView
     /// objects provide an array-like view over an underlying data buffer, but one which allows
     /// items of different size and type in the ArrayBuffer.
@@ -53,14 +92,16 @@ impl JsArrayBuffer {
     ///
     /// JavaScript TypedArray objects are described in Section 22.2 of the ECMAScript Language
     /// Specification.
-    pub fn typedarray(
+    ///
+    /// The kind of TypedArray is decided by the element type `T`, e.g. `typedarray::<f32>`
+    /// creates a Float32Array.
+    pub fn typedarray<T: TypedArrayElement>(
         &self,
-        typed: NapiTypedarrayType,
         offset: usize,
         length: usize,
-    ) -> NapiResult<JsTypedArray> {
+    ) -> NapiResult<JsTypedArray<T>> {
         let typed =
-            napi_call!(=napi_create_typedarray, self.env(), typed, length, self.raw(), offset);
+            napi_call!(=napi_create_typedarray, self.env(), T::TYPE, length, self.raw(), offset);
         Ok(JsTypedArray::from_raw(self.env(), typed))
     }
 
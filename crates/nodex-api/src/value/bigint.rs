@@ -1,32 +1,175 @@
 use crate::{api, prelude::*};
-use std::marker::PhantomData;
 
+/// Js BigInt of arbitrary precision.
+///
+/// The magnitude of a BigInt is exchanged as little-endian 64-bit words, i.e. `words[0]` is the
+/// least significant word, together with a sign which is true for negative values.
 #[derive(Copy, Clone, Debug)]
-pub struct JsBigInt<T: Copy>(pub(crate) JsValue, PhantomData<T>);
+pub struct JsBigInt(pub(crate) JsValue);
 
-impl<T: Copy> JsBigInt<T> {
-    pub(crate) fn from_value(value: JsValue) -> JsBigInt<T> {
-        JsBigInt(value, PhantomData)
+impl JsBigInt {
+    pub(crate) fn from_value(value: JsValue) -> JsBigInt {
+        JsBigInt(value)
     }
 
     #[cfg(feature = "v6")]
     /// This API converts the C int64_t type to the JavaScript BigInt type.
-    pub fn new_i64(env: NapiEnv, value: i64) -> NapiResult<JsBigInt<i64>> {
+    pub fn new_i64(env: NapiEnv, value: i64) -> NapiResult<JsBigInt> {
         let value = napi_call!(=napi_create_bigint_int64, env, value);
         Ok(JsBigInt::from_raw(env, value))
     }
 
     #[cfg(feature = "v6")]
     /// This API converts the C unt64_t type to the JavaScript BigInt type.
-    pub fn new_u64(env: NapiEnv, value: u64) -> NapiResult<JsBigInt<u64>> {
+    pub fn new_u64(env: NapiEnv, value: u64) -> NapiResult<JsBigInt> {
         let value = napi_call!(=napi_create_bigint_uint64, env, value);
         Ok(JsBigInt::from_raw(env, value))
     }
+
+    #[cfg(feature = "v6")]
+    /// This API converts an array of unsigned 64-bit words into a single BigInt value.
+    ///
+    /// The resulting BigInt is calculated as: (–1)^sign_bit (words[0] × (2^64)^0 + words[1] ×
+    /// (2^64)^1 + …)
+    pub fn from_words(env: NapiEnv, negative: bool, words: &[u64]) -> NapiResult<JsBigInt> {
+        let value = napi_call!(
+            =napi_create_bigint_words,
+            env,
+            negative as _,
+            words.len(),
+            words.as_ptr(),
+        );
+        Ok(JsBigInt::from_raw(env, value))
+    }
+
+    #[cfg(feature = "v6")]
+    /// Convert the rust i128 type to the JavaScript BigInt type.
+    pub fn from_i128(env: NapiEnv, value: i128) -> NapiResult<JsBigInt> {
+        JsBigInt::from_words(env, value < 0, &split(value.unsigned_abs()))
+    }
+
+    #[cfg(feature = "v6")]
+    /// Convert the rust u128 type to the JavaScript BigInt type.
+    pub fn from_u128(env: NapiEnv, value: u128) -> NapiResult<JsBigInt> {
+        JsBigInt::from_words(env, false, &split(value))
+    }
+
+    #[cfg(feature = "v6")]
+    /// This API returns the C int64_t primitive equivalent of the given JavaScript BigInt,
+    /// together with whether the conversion is lossless.
+    ///
+    /// If needed it will truncate the value, and the second field is false then.
+    pub fn get_i64(&self) -> NapiResult<(i64, bool)> {
+        let (mut value, mut lossless) = (0, false);
+        napi_call!(
+            napi_get_value_bigint_int64,
+            self.env(),
+            self.raw(),
+            &mut value,
+            &mut lossless,
+        );
+        Ok((value, lossless))
+    }
+
+    #[cfg(feature = "v6")]
+    /// This API returns the C uint64_t primitive equivalent of the given JavaScript BigInt,
+    /// together with whether the conversion is lossless.
+    ///
+    /// If needed it will truncate the value, and the second field is false then.
+    pub fn get_u64(&self) -> NapiResult<(u64, bool)> {
+        let (mut value, mut lossless) = (0, false);
+        napi_call!(
+            napi_get_value_bigint_uint64,
+            self.env(),
+            self.raw(),
+            &mut value,
+            &mut lossless,
+        );
+        Ok((value, lossless))
+    }
+
+    #[cfg(feature = "v6")]
+    /// The number of 64-bit words needed to represent the magnitude of this BigInt.
+    pub fn word_count(&self) -> NapiResult<usize> {
+        let mut count = 0;
+        napi_call!(
+            napi_get_value_bigint_words,
+            self.env(),
+            self.raw(),
+            std::ptr::null_mut(),
+            &mut count,
+            std::ptr::null_mut(),
+        );
+        Ok(count)
+    }
+
+    #[cfg(feature = "v6")]
+    /// This API converts a single BigInt value into a sign and an array of little-endian
+    /// unsigned 64-bit words, the sign is true for negative values.
+    pub fn to_words(&self) -> NapiResult<(bool, Vec<u64>)> {
+        let mut count = self.word_count()?;
+        let mut words = vec![0u64; count];
+        let mut sign = std::mem::MaybeUninit::uninit();
+        napi_call!(
+            napi_get_value_bigint_words,
+            self.env(),
+            self.raw(),
+            sign.as_mut_ptr(),
+            &mut count,
+            words.as_mut_ptr(),
+        );
+        words.truncate(count);
+        unsafe { Ok((sign.assume_init() != 0, words)) }
+    }
+
+    #[cfg(feature = "v6")]
+    /// The i128 equivalent of this BigInt, None if it does not fit in an i128.
+    pub fn to_i128(&self) -> NapiResult<Option<i128>> {
+        let (negative, words) = self.to_words()?;
+        Ok(join(&words).and_then(|magnitude| {
+            if negative {
+                // NB: i128::MIN has no positive counterpart
+                if magnitude > i128::MIN.unsigned_abs() {
+                    None
+                } else {
+                    Some(0u128.wrapping_sub(magnitude) as i128)
+                }
+            } else {
+                i128::try_from(magnitude).ok()
+            }
+        }))
+    }
+
+    #[cfg(feature = "v6")]
+    /// The u128 equivalent of this BigInt, None if it is negative or does not fit in an u128.
+    pub fn to_u128(&self) -> NapiResult<Option<u128>> {
+        let (negative, words) = self.to_words()?;
+        Ok(join(&words).filter(|magnitude| !negative || *magnitude == 0))
+    }
+}
+
+/// Split an u128 into little-endian words.
+#[cfg(feature = "v6")]
+fn split(value: u128) -> [u64; 2] {
+    [value as u64, (value >> 64) as u64]
 }
 
-impl<T: Copy> NapiValueT for JsBigInt<T> {
-    fn from_raw(env: NapiEnv, raw: napi_value) -> JsBigInt<T> {
-        JsBigInt(JsValue(env, raw), PhantomData)
+/// Join little-endian words into an u128, None if the value does not fit.
+#[cfg(feature = "v6")]
+fn join(words: &[u64]) -> Option<u128> {
+    match words {
+        [] => Some(0),
+        [lo] => Some(*lo as u128),
+        [lo, hi, rest @ ..] if rest.iter().all(|word| *word == 0) => {
+            Some(*lo as u128 | (*hi as u128) << 64)
+        }
+        _ => None,
+    }
+}
+
+impl NapiValueT for JsBigInt {
+    fn from_raw(env: NapiEnv, raw: napi_value) -> JsBigInt {
+        JsBigInt(JsValue(env, raw))
     }
 
     fn value(&self) -> JsValue {
@@ -34,8 +177,107 @@ impl<T: Copy> NapiValueT for JsBigInt<T> {
     }
 }
 
-impl<T: Copy> NapiValueCheck for JsBigInt<T> {
+impl NapiValueCheck for JsBigInt {
     fn check(&self) -> NapiResult<bool> {
         Ok(self.kind()? == NapiValuetype::Bigint)
     }
 }
+
+/// 128-bit integers are converted from and to js bigints, `NapiStatus::InvalidArg` is returned
+/// if the bigint does not fit in the target type.
+#[cfg(feature = "v6")]
+mod convert {
+    use super::*;
+
+    impl ToJs for i128 {
+        type Value = JsBigInt;
+
+        fn to_js(self, env: NapiEnv) -> NapiResult<JsBigInt> {
+            JsBigInt::from_i128(env, self)
+        }
+    }
+
+    impl FromJs for i128 {
+        fn from_js(value: JsValue) -> NapiResult<i128> {
+            value.as_bigint()?.to_i128()?.ok_or(NapiStatus::InvalidArg)
+        }
+    }
+
+    impl ToJs for u128 {
+        type Value = JsBigInt;
+
+        fn to_js(self, env: NapiEnv) -> NapiResult<JsBigInt> {
+            JsBigInt::from_u128(env, self)
+        }
+    }
+
+    impl FromJs for u128 {
+        fn from_js(value: JsValue) -> NapiResult<u128> {
+            value.as_bigint()?.to_u128()?.ok_or(NapiStatus::InvalidArg)
+        }
+    }
+}
+
+/// Interop with the `num-bigint` crate, `BigUint` rejects negative bigints with
+/// `NapiStatus::InvalidArg`.
+#[cfg(feature = "num-bigint")]
+mod num {
+    use super::*;
+    use num_bigint::{BigInt, BigUint, Sign};
+
+    impl JsBigInt {
+        /// Convert a `num_bigint::BigInt` to the JavaScript BigInt type.
+        pub fn from_bigint(env: NapiEnv, value: &BigInt) -> NapiResult<JsBigInt> {
+            JsBigInt::from_words(
+                env,
+                value.sign() == Sign::Minus,
+                &value.magnitude().to_u64_digits(),
+            )
+        }
+
+        /// The `num_bigint::BigInt` equivalent of this BigInt.
+        pub fn to_bigint(&self) -> NapiResult<BigInt> {
+            let (negative, words) = self.to_words()?;
+            let magnitude = BigUint::from_bytes_le(
+                &words
+                    .iter()
+                    .flat_map(|word| word.to_le_bytes())
+                    .collect::<Vec<_>>(),
+            );
+            let sign = if negative { Sign::Minus } else { Sign::Plus };
+            Ok(BigInt::from_biguint(sign, magnitude))
+        }
+    }
+
+    impl ToJs for BigInt {
+        type Value = JsBigInt;
+
+        fn to_js(self, env: NapiEnv) -> NapiResult<JsBigInt> {
+            JsBigInt::from_bigint(env, &self)
+        }
+    }
+
+    impl FromJs for BigInt {
+        fn from_js(value: JsValue) -> NapiResult<BigInt> {
+            value.as_bigint()?.to_bigint()
+        }
+    }
+
+    impl ToJs for BigUint {
+        type Value = JsBigInt;
+
+        fn to_js(self, env: NapiEnv) -> NapiResult<JsBigInt> {
+            JsBigInt::from_words(env, false, &self.to_u64_digits())
+        }
+    }
+
+    impl FromJs for BigUint {
+        fn from_js(value: JsValue) -> NapiResult<BigUint> {
+            value
+                .as_bigint()?
+                .to_bigint()?
+                .to_biguint()
+                .ok_or(NapiStatus::InvalidArg)
+        }
+    }
+}
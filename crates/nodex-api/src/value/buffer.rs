@@ -1,129 +1,180 @@
 use crate::{api, prelude::*};
 use std::{mem::MaybeUninit, os::raw::c_char};
 
+/// Js Buffer, the length is known at runtime by `len()`.
 #[derive(Copy, Clone, Debug)]
-pub struct JsBuffer<const N: usize>(pub(crate) JsValue);
+pub struct JsBuffer(pub(crate) JsValue);
 
-impl<const N: usize> JsBuffer<N> {
-    pub(crate) fn from_value(value: JsValue) -> JsBuffer<N> {
+impl JsBuffer {
+    pub(crate) fn from_value(value: JsValue) -> JsBuffer {
         JsBuffer(value)
     }
 
-    /// This API allocates a node::Buffer object. While this is still a fully-supported data
-    /// structure, in most cases using a TypedArray will suffice.
-    pub fn create(env: NapiEnv) -> NapiResult<JsBuffer<N>> {
-        let buffer = napi_call!(=napi_create_buffer, env, N, std::ptr::null_mut());
-        Ok(JsBuffer::<N>::from_raw(env, buffer))
+    /// This API allocates a node::Buffer object of `len` zero bytes. While this is still a
+    /// fully-supported data structure, in most cases using a TypedArray will suffice.
+    pub fn new(env: NapiEnv, len: usize) -> NapiResult<JsBuffer> {
+        let mut data = MaybeUninit::uninit();
+        let buffer = napi_call!(=napi_create_buffer, env, len, data.as_mut_ptr());
+        unsafe {
+            let data = data.assume_init();
+            if len > 0 {
+                // NB: node may hand out uninitialized memory for a new Buffer
+                std::ptr::write_bytes(data as *mut u8, 0, len);
+            }
+        }
+        Ok(JsBuffer::from_raw(env, buffer))
     }
 
     /// This API allocates a node::Buffer object and initializes it with data copied from the
     /// passed-in buffer. While this is still a fully-supported data structure, in most cases using
     /// a TypedArray will suffice.
-    pub fn create_copy(env: NapiEnv, data: [u8; N]) -> NapiResult<JsBuffer<N>> {
+    pub fn from_slice(env: NapiEnv, data: impl AsRef<[u8]>) -> NapiResult<JsBuffer> {
+        let data = data.as_ref();
         let buffer = napi_call!(
             =napi_create_buffer_copy,
             env,
-            N,
-            data.as_ref().as_ptr() as _,
+            data.len(),
+            data.as_ptr() as _,
             std::ptr::null_mut(),
         );
-        Ok(JsBuffer::<N>::from_raw(env, buffer))
-    }
-
-    // pub fn create_external(
-    //     env: NapiEnv,
-    //     data: impl AsRef<[u8]>,
-    //     finalizer: impl FnOnce(NapiEnv, [u8; N]) -> NapiResult<()> + 'static,
-    // ) -> NapiResult<JsBuffer<N>>
-    // {
-    //     type FnOnceBoxed<const N: usize> = Box<dyn FnOnce(NapiEnv, [u8; N]) -> NapiResult<()>>;
-    //
-    //     unsafe extern "C" fn finalize<const N: usize>(
-    //         env: NapiEnv,
-    //         data: DataPointer,
-    //         hint: DataPointer,
-    //     ) {
-    //         let ext: [u8; N] = *(data as *const [u8; N]);
-    //         let finalizer: Box<FnOnceBoxed::<N>> = Box::from_raw(hint as _);
-    //         if let Err(e) = finalizer(env, ext) {
-    //             log::error!("JsExternal::<T>::finalize: {}", e);
-    //         }
-    //         std::ptr::drop_in_place(data as *mut [u8; N]);
-    //     }
-    //
-    //     let finalizer: Box<FnOnceBoxed::<N>> = Box::new(Box::new(finalizer));
-    //     let data = std::mem::ManuallyDrop::new([10; 10]);
-    //
-    //     let buffer = napi_call!(
-    //         =napi_create_external_buffer,
-    //         env,
-    //         N,
-    //         data.as_ptr() as DataPointer,
-    //         Some(finalize::<N>),
-    //         Box::into_raw(finalizer) as DataPointer,
-    //     );
-    //
-    //     Ok(JsBuffer::from_raw(env, buffer))
-    // }
-
-    /// Get the underlaying array
-    pub fn get(&self) -> NapiResult<&[u8]> {
-        let mut data = MaybeUninit::uninit();
-        let length = napi_call!(=napi_get_buffer_info, self.env(), self.raw(), data.as_mut_ptr());
-        if length != N {
-            return Err(NapiStatus::InvalidArg);
+        Ok(JsBuffer::from_raw(env, buffer))
+    }
+
+    /// Create a node::Buffer object backed by the bytes of `data` without copying, the vec is
+    /// dropped when the Buffer is garbage-collected. See JsBuffer::from_external().
+    pub fn from_vec(env: NapiEnv, data: Vec<u8>) -> NapiResult<JsBuffer> {
+        JsBuffer::from_external(env, data)
+    }
+
+    /// This API allocates a node::Buffer object backed by the bytes of `data` without copying.
+    /// The ownership of `data` is moved into a finalizer, which drops it when the Buffer is
+    /// garbage-collected.
+    ///
+    /// The bytes are writable from js, so `data` must own them exclusively, and `data.as_mut()`
+    /// must keep returning the same slice, at the same address and of the same length, until
+    /// `data` is dropped, which holds for `Vec<u8>`, `Box<[u8]>` or any type owning a heap
+    /// allocation which it does not change.
+    pub fn from_external<D>(env: NapiEnv, data: D) -> NapiResult<JsBuffer>
+    where
+        D: AsMut<[u8]> + Send + 'static,
+    {
+        let mut data = Box::new(data);
+        let bytes = (*data).as_mut();
+        if bytes.is_empty() {
+            return JsBuffer::new(env, 0);
         }
+        let (ptr, len) = (bytes.as_mut_ptr(), bytes.len());
 
-        unsafe {
-            let data = data.assume_init();
-            Ok(std::slice::from_raw_parts(data as *mut u8, N))
+        let data = Box::into_raw(data);
+        let (status, buffer) = napi_call!(
+            ?napi_create_external_buffer,
+            env,
+            len,
+            ptr as DataPointer,
+            Some(finalize_external::<D>),
+            data as DataPointer,
+        );
+        if status.err() {
+            // NB: the finalizer is not called if it fails
+            unsafe {
+                drop(Box::from_raw(data));
+            }
+            return Err(status);
         }
+
+        Ok(JsBuffer::from_raw(env, buffer))
     }
 
-    /// Get the underlaying array (mut)
-    pub fn get_mut(&mut self) -> NapiResult<&mut [u8]> {
+    /// This API is used to retrieve the underlying data buffer of a node::Buffer and its length.
+    ///
+    /// WARNING: Use caution while using this API since the underlying data buffer's lifetime is
+    /// not guaranteed if it's managed by the VM.
+    fn info(&self) -> NapiResult<(*mut u8, usize)> {
         let mut data = MaybeUninit::uninit();
         let length = napi_call!(=napi_get_buffer_info, self.env(), self.raw(), data.as_mut_ptr());
-        if length != N {
-            return Err(NapiStatus::InvalidArg);
+        unsafe { Ok((data.assume_init() as *mut u8, length)) }
+    }
+
+    /// View the bytes of this buffer.
+    pub fn as_slice(&self) -> NapiResult<&[u8]> {
+        let (data, len) = self.info()?;
+        if len == 0 {
+            return Ok(&[]);
         }
+        unsafe { Ok(std::slice::from_raw_parts(data, len)) }
+    }
 
-        unsafe {
-            let data = data.assume_init();
-            Ok(std::slice::from_raw_parts_mut(data as *mut u8, N))
+    /// View the bytes of this buffer (mut).
+    pub fn as_mut_slice(&mut self) -> NapiResult<&mut [u8]> {
+        let (data, len) = self.info()?;
+        if len == 0 {
+            return Ok(&mut []);
         }
+        unsafe { Ok(std::slice::from_raw_parts_mut(data, len)) }
+    }
+
+    #[deprecated = "use JsBuffer::as_slice() instead"]
+    pub fn get(&self) -> NapiResult<&[u8]> {
+        self.as_slice()
+    }
+
+    #[deprecated = "use JsBuffer::as_mut_slice() instead"]
+    pub fn get_mut(&mut self) -> NapiResult<&mut [u8]> {
+        self.as_mut_slice()
     }
 
     /// The length of current buffer.
-    pub const fn len(&self) -> usize {
-        N
+    pub fn len(&self) -> NapiResult<usize> {
+        Ok(self.info()?.1)
     }
 
     /// The buffer is empty
-    pub const fn is_empty(&self) -> bool {
-        N == 0
+    pub fn is_empty(&self) -> NapiResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// For Node.js >=4 Buffers are Uint8Arrays, view this buffer as a `JsTypedArray<u8>`
+    /// sharing the same memory.
+    pub fn typedarray(&self) -> JsTypedArray<u8> {
+        JsTypedArray::from_value(self.0)
     }
-}
 
-impl<const N: usize> NapiValueT for JsBuffer<N> {
-    fn from_raw(env: NapiEnv, raw: napi_value) -> JsBuffer<N> {
-        JsBuffer(JsValue(env, raw))
+    /// Create a node::Buffer with the bytes copied from a `JsTypedArray<u8>`.
+    pub fn from_typedarray(array: JsTypedArray<u8>) -> NapiResult<JsBuffer> {
+        JsBuffer::from_slice(array.env(), array.as_slice()?)
     }
+}
+
+napi_value_t!(JsBuffer);
 
-    fn value(&self) -> JsValue {
-        self.0
+impl From<JsBuffer> for JsTypedArray<u8> {
+    fn from(buffer: JsBuffer) -> JsTypedArray<u8> {
+        buffer.typedarray()
     }
 }
 
-impl<const N: usize> std::ops::Index<usize> for JsBuffer<N> {
+impl std::ops::Index<usize> for JsBuffer {
     type Output = u8;
     fn index(&self, idx: usize) -> &Self::Output {
-        &self.get().unwrap()[idx]
+        &self.as_slice().unwrap()[idx]
     }
 }
 
-impl<const N: usize> NapiValueCheck for JsBuffer<N> {
+impl NapiValueCheck for JsBuffer {
     fn check(&self) -> NapiResult<bool> {
         Ok(napi_call!(=napi_is_buffer, self.env(), self.raw()))
     }
 }
+
+/// Finalizer of the external Buffers and ArrayBuffers, the owner of the bytes is passed as hint.
+pub(crate) unsafe extern "C" fn finalize_external<D>(
+    _: NapiEnv,
+    _: DataPointer,
+    hint: DataPointer,
+) {
+    let data = Box::from_raw(hint as *mut D);
+    crate::callback::guard("finalize_external", move || {
+        drop(data);
+        Ok(())
+    });
+}
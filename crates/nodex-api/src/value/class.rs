@@ -1,5 +1,11 @@
-use crate::{api, prelude::*};
-use std::{mem::MaybeUninit, os::raw::c_char};
+use crate::{api, callback::Live, descriptor::Prepared, prelude::*};
+use std::{
+    any::Any,
+    cell::{OnceCell, RefCell},
+    mem::MaybeUninit,
+    os::raw::c_char,
+    rc::Rc,
+};
 
 #[derive(Copy, Clone, Debug)]
 pub struct JsClass(pub(crate) JsValue);
@@ -30,6 +36,10 @@ impl JsClass {
     /// of the napi_property_descriptor array items can be associated with the resulting JavaScript
     /// constructor (which is returned in the result parameter) and freed whenever the class is
     /// garbage-collected by passing both the JavaScript function and the data to napi_add_finalizer.
+    ///
+    /// NB: v8 caches the classes created from templates, so a class and the closures of its
+    /// constructor and methods live until the env is torn down. Define a class once and keep it,
+    /// e.g. by `NapiClassRef`.
     #[allow(clippy::type_complexity)]
     pub fn new<F, P, T, R>(
         env: NapiEnv,
@@ -43,76 +53,113 @@ impl JsClass {
         F: FnMut(JsObject, T) -> NapiResult<R>,
         P: AsRef<[NapiPropertyDescriptor]>,
     {
-        // NB: leak the func closure
-        let func: Box<Box<dyn FnMut(JsObject, T) -> NapiResult<R>>> = Box::new(Box::new(func));
-
-        // TODO: it just works but not very useful by current design
-        // use the trampoline function to call into the closure
         extern "C" fn trampoline<T: FromJsArgs, R: NapiValueT>(
             env: NapiEnv,
             info: napi_callback_info,
         ) -> napi_value {
-            let mut data = MaybeUninit::uninit();
-            let mut this = MaybeUninit::uninit();
-
-            let (argc, argv, this, mut func) = unsafe {
-                let mut argc = T::len();
-                let mut argv = vec![std::ptr::null_mut(); T::len()];
-
-                let status = api::napi_get_cb_info(
-                    env,
-                    info,
-                    &mut argc,
-                    argv.as_mut_ptr(),
-                    this.as_mut_ptr(),
-                    data.as_mut_ptr(),
-                );
-
-                // NB: the JsFunction maybe called multiple times, so we can should leak the
-                // closure memory here.
-                //
-                // With napi >= 5, we can add a finalizer to this function.
-                let func: &mut Box<dyn FnMut(JsObject, T) -> NapiResult<R>> =
-                    std::mem::transmute(data);
-
-                (argc, argv, this.assume_init(), func)
-            };
-
-            let args = argv
-                .into_iter()
-                .map(|arg| JsValue::from_raw(env, arg))
-                .collect();
-            let this = JsObject::from_raw(env, this);
-
-            if let Ok(args) = T::from_js_args(JsArgs(args)) {
-                napi_r!(env, =func(this, args))
-            } else {
-                env.throw_error("wrong argument type!");
-                env.undefined().unwrap().raw()
-            }
+            crate::callback::invoke(env, || {
+                let info = CallbackInfo::from_raw(env, info);
+                let (this, args, data) = info.get(T::len())?;
+                // NB: the closure lives as long as the class
+                let func =
+                    unsafe { &mut *(data as *mut Box<dyn FnMut(JsObject, T) -> NapiResult<R>>) };
+                let args = crate::args::from_call::<T>(env, args)?;
+                Ok(func(this, args)?.raw())
+            })
+        }
+
+        let func: Box<dyn FnMut(JsObject, T) -> NapiResult<R>> = Box::new(func);
+        JsClass::define(env, name, trampoline::<T, R>, func, properties)
+    }
+
+    /// Defines a JavaScript class whose constructor is a rust closure taking the `CallContext`
+    /// of each call, `data` is shared by the calls as `CallContext::data()`.
+    ///
+    /// The constructor is also called without `new`, e.g. by `Foo()`, then
+    /// `CallContext::new_target()` is None.
+    pub fn with_context<D, R>(
+        env: NapiEnv,
+        name: impl AsRef<str>,
+        data: D,
+        func: impl FnMut(&CallContext<D>) -> NapiResult<R> + 'static,
+        properties: impl AsRef<[NapiPropertyDescriptor]>,
+    ) -> NapiResult<JsClass>
+    where
+        D: 'static,
+        R: NapiValueT,
+    {
+        type Closure<D, R> = (D, Box<dyn FnMut(&CallContext<D>) -> NapiResult<R>>);
+
+        extern "C" fn trampoline<D, R: NapiValueT>(
+            env: NapiEnv,
+            info: napi_callback_info,
+        ) -> napi_value {
+            crate::callback::invoke(env, || {
+                let info = CallbackInfo::from_raw(env, info);
+                let (this, args, data) = info.get(crate::callback::ARGS_CAPACITY)?;
+                // NB: the closure lives as long as the class
+                let (data, func) = unsafe { &mut *(data as *mut Closure<D, R>) };
+                Ok(func(&CallContext::new(info, this, args, data))?.raw())
+            })
         }
 
-        let fn_pointer = Box::into_raw(func) as DataPointer;
-        let value = napi_call!(
-            =napi_define_class,
+        let closure: Closure<D, R> = (data, Box::new(func));
+        JsClass::define(env, name, trampoline::<D, R>, closure, properties)
+    }
+
+    /// Define a class calling `callback` with `closure` as its data, the closure is freed when
+    /// the class is garbage-collected.
+    fn define<C>(
+        env: NapiEnv,
+        name: impl AsRef<str>,
+        callback: extern "C" fn(NapiEnv, napi_callback_info) -> napi_value,
+        closure: C,
+        properties: impl AsRef<[NapiPropertyDescriptor]>,
+    ) -> NapiResult<JsClass> {
+        // NB: the closures of the methods and accessors are owned by the functions and the
+        // objects they are defined on, so they are defined after the class is created
+        let (defined, declared): (Vec<_>, Vec<_>) = properties
+            .as_ref()
+            .iter()
+            .cloned()
+            .partition(NapiPropertyDescriptor::has_closures);
+        let properties = Prepared::new(env, &declared)?;
+
+        let fn_pointer = Box::into_raw(Box::new(closure)) as DataPointer;
+        let (status, value) = napi_call!(
+            ?napi_define_class,
             env,
             name.as_ref().as_ptr() as CharPointer,
             name.as_ref().len(),
-            Some(trampoline::<T, R>),
+            Some(callback),
             fn_pointer,
-            properties.as_ref().len(),
-            properties.as_ref().as_ptr() as *const _,
+            properties.raw().len(),
+            properties.raw().as_ptr(),
         );
 
-        let mut class = JsClass(JsValue(env, value));
+        let free = move || unsafe {
+            let _: Box<C> = Box::from_raw(fn_pointer as _);
+        };
+        if status.err() {
+            free();
+            return Err(status);
+        }
 
-        class.gc(move |_| unsafe {
-            // NB: the leaked data is collected here.
-            let _: Box<Box<dyn FnMut(JsObject, T) -> NapiResult<R>>> =
-                Box::from_raw(fn_pointer as _);
+        let mut class = JsClass(JsValue(env, value));
+        let live = Live::new();
+        class.gc(move |_| {
+            free();
+            drop(live);
             Ok(())
         })?;
 
+        let (statics, methods): (Vec<_>, Vec<_>) = defined
+            .into_iter()
+            .partition(NapiPropertyDescriptor::is_static);
+        class.define_properties(statics)?;
+        let prototype: JsObject = unsafe { class.value().cast::<JsObject>() }.get("prototype")?;
+        prototype.define_properties(methods)?;
+
         Ok(class)
     }
 
@@ -133,11 +180,78 @@ impl JsClass {
             =napi_new_instance,
             self.env(),
             self.raw(),
-            T::len(),
+            args.len(),
             args.as_ptr(),
         );
         Ok(JsObject::from_raw(self.env(), instance))
     }
+
+    /// Instantiate a `NapiClass` with an existing native instance, the constructor takes the
+    /// instance instead of running the rust constructor.
+    pub fn new_instance_with<T: NapiClass>(&self, instance: T) -> NapiResult<JsObject> {
+        NATIVE_INSTANCE.with(|native| native.replace(Some(Box::new(instance))));
+        let object = self.new_instance(());
+        // NB: drop the instance if the constructor did not take it
+        NATIVE_INSTANCE.with(|native| native.take());
+        object
+    }
+}
+
+thread_local! {
+    /// native instance passed from `JsClass::new_instance_with` to the class constructor
+    static NATIVE_INSTANCE: RefCell<Option<Box<dyn Any>>> = RefCell::new(None);
+}
+
+/// Take the native instance passed by `JsClass::new_instance_with`, used by the constructor
+/// generated by `#[nodex::methods]`.
+#[doc(hidden)]
+pub fn take_native_instance<T: 'static>() -> Option<T> {
+    NATIVE_INSTANCE.with(|native| {
+        let mut native = native.borrow_mut();
+        match native.take()?.downcast::<T>() {
+            Ok(instance) => Some(*instance),
+            Err(other) => {
+                native.replace(other);
+                None
+            }
+        }
+    })
+}
+
+/// Rust types exported as js classes, implemented by `#[nodex::class]`.
+pub trait NapiClass: Sized + 'static {
+    /// The js class name.
+    const NAME: &'static str;
+}
+
+/// The constructor, methods and accessors of a `NapiClass`, implemented by
+/// `#[nodex::methods]`.
+pub trait NapiClassMethods: NapiClass {
+    /// Define the js class, instances of it wrap a native `Self`.
+    fn define(env: NapiEnv) -> NapiResult<JsClass>;
+}
+
+/// A weak reference to a defined class, shared by its methods to create new instances.
+#[derive(Clone, Default)]
+pub struct NapiClassRef(Rc<OnceCell<NapiRef>>);
+
+impl NapiClassRef {
+    /// Remember the defined class, can only be set once.
+    pub fn set(&self, class: JsClass) -> NapiResult<()> {
+        self.0
+            .set(NapiRef::new(class, 0)?)
+            .map_err(|_| NapiStatus::GenericFailure)
+    }
+
+    /// Get the defined class.
+    pub fn get(&self) -> NapiResult<JsClass> {
+        self.0.get().ok_or(NapiStatus::GenericFailure)?.deref()
+    }
+
+    /// Create a new js instance of the class wrapping `instance`.
+    pub fn new_instance<T: NapiClass>(&self, instance: T) -> NapiResult<JsObject> {
+        self.get()?.new_instance_with(instance)
+    }
 }
 
 napi_value_t!(JsClass);
@@ -1,5 +1,5 @@
 use crate::{api, prelude::*};
-use std::{marker::PhantomData, mem::MaybeUninit, os::raw::c_void};
+use std::{marker::PhantomData, os::raw::c_void};
 
 #[derive(Copy, Clone, Debug)]
 pub struct JsExternal<T>(pub(crate) JsValue, PhantomData<T>);
@@ -12,6 +12,8 @@ impl<T> JsExternal<T> {
     /// This API allocates a JavaScript value with external data attached to it. This is used to
     /// pass external data through JavaScript code, so it can be retrieved later by native code
     /// using napi_get_value_external.
+    ///
+    /// NB: the external is tagged with the type tag of `T`, see `nodex::tag`.
     pub fn new(
         env: NapiEnv,
         value: T,
@@ -19,14 +21,15 @@ impl<T> JsExternal<T> {
     ) -> NapiResult<JsExternal<T>> {
         type FnOnceBoxed<T> = Box<dyn FnOnce(NapiEnv, T) -> NapiResult<()>>;
         // NB: first leak value.
-        let value = Box::into_raw(Box::new(value));
+        let value = Box::into_raw(Box::new(Tagged {
+            tag: NapiTypeTag::of::<T>(),
+            value,
+        }));
 
         unsafe extern "C" fn finalize<T>(env: NapiEnv, data: DataPointer, hint: DataPointer) {
-            let ext: Box<T> = Box::from_raw(data as *mut T);
+            let ext: Box<Tagged<T>> = Box::from_raw(data as *mut Tagged<T>);
             let finalizer: Box<FnOnceBoxed<T>> = Box::from_raw(hint as _);
-            if let Err(e) = finalizer(env, *ext) {
-                log::error!("JsExternal::<T>::finalize: {}", e);
-            }
+            crate::callback::guard("JsExternal::<T>::finalize", || finalizer(env, ext.value));
         }
 
         let finalizer: Box<FnOnceBoxed<T>> = Box::new(Box::new(finalizer));
@@ -39,37 +42,40 @@ impl<T> JsExternal<T> {
             Box::into_raw(finalizer) as DataPointer,
         );
 
-        Ok(JsExternal(JsValue::from_raw(env, value), PhantomData))
+        let external = JsExternal(JsValue::from_raw(env, value), PhantomData);
+        #[cfg(feature = "v8")]
+        crate::tag::tag_object(external.value(), &NapiTypeTag::of::<T>())?;
+        Ok(external)
     }
 
-    /// Access the underlaying data.
-    pub fn get(&self) -> NapiResult<&mut T> {
-        let ext = napi_call!(=napi_get_value_external, self.env(), self.raw());
-        unsafe { Ok(&mut *(ext as *mut T)) }
-    }
+    /// Access the underlaying data, `TypeTagError::Mismatch` is returned if the external is not
+    /// created by `JsExternal::<T>::new`.
+    pub fn get(&self) -> Result<&mut T, TypeTagError> {
+        let tag = NapiTypeTag::of::<T>();
+        #[cfg(feature = "v8")]
+        if !crate::tag::check_object(self.value(), &tag)? {
+            return Err(TypeTagError::mismatch::<T>());
+        }
 
-    /// This API returns a Node-API value corresponding to a JavaScript ArrayBuffer. The underlying byte buffer of the ArrayBuffer is externally allocated and managed. The caller must ensure that the byte buffer remains valid until the finalize callback is called.
-    /// The API adds a napi_finalize callback which will be called when the JavaScript object just created is ready for garbage collection. It is similar to napi_wrap() except that:
-    /// - the native data cannot be retrieved later using napi_unwrap(),
-    /// - nor can it be removed later using napi_remove_wrap(), and
-    /// - the object created by the API can be used with napi_wrap().
-    /// JavaScript ArrayBuffers are described in Section 24.1 of the ECMAScript Language Specification.
-    pub fn arraybuffer<'a>(
-        env: NapiEnv,
-        value: impl AsRef<[T]>,
-    ) -> NapiResult<JsExternal<&'a [T]>> {
-        todo!()
+        let (status, ext) = napi_call!(?napi_get_value_external, self.env(), self.raw());
+        if status.err() {
+            return Err(status.into());
+        }
+        // NB: the tag is read first, as `Tagged` is `repr(C)` with the tag first, the data is
+        // only viewed as `Tagged<T>` once the tag matches
+        if unsafe { *(ext as *const NapiTypeTag) } != tag {
+            return Err(TypeTagError::mismatch::<T>());
+        }
+        let ext = unsafe { &mut *(ext as *mut Tagged<T>) };
+        Ok(&mut ext.value)
     }
+}
 
-    /// This API allocates a node::Buffer object and initializes it with data backed by the passed in buffer. While this is still a fully-supported data structure, in most cases using a TypedArray will suffice.
-    /// The API adds a napi_finalize callback which will be called when the JavaScript object just created is ready for garbage collection. It is similar to napi_wrap() except that:
-    /// - the native data cannot be retrieved later using napi_unwrap(),
-    /// - nor can it be removed later using napi_remove_wrap(), and
-    /// - the object created by the API can be used with napi_wrap().
-    /// For Node.js >=4 Buffers are Uint8Arrays.
-    pub fn buffer<'a>(env: NapiEnv, value: impl AsRef<[T]>) -> NapiResult<JsExternal<&'a [T]>> {
-        todo!()
-    }
+/// The data of an external, the type tag is kept before the value.
+#[repr(C)]
+struct Tagged<T> {
+    tag: NapiTypeTag,
+    value: T,
 }
 
 impl<T> NapiValueT for JsExternal<T> {
@@ -1,5 +1,5 @@
-use crate::{api, prelude::*};
-use std::{marker::PhantomData, mem::MaybeUninit, os::raw::c_char};
+use crate::{api, callback::Live, prelude::*};
+use std::{marker::PhantomData, os::raw::c_char};
 
 #[derive(Copy, Clone, Debug)]
 pub struct Function<F: NapiValueT>(pub(crate) JsValue, PhantomData<F>);
@@ -58,91 +58,98 @@ impl<F: NapiValueT> Function<F> {
         env: NapiEnv,
         name: Option<impl AsRef<str>>,
         func: impl FnMut(JsObject, T) -> NapiResult<R>,
-    ) -> NapiResult<Function<R>>
+    ) -> NapiResult<Function<R::Value>>
     where
         T: FromJsArgs,
-        R: NapiValueT,
+        R: ToJs,
     {
-        let (name, len) = if let Some(name) = name {
-            (name.as_ref().as_ptr() as *const c_char, name.as_ref().len())
-        } else {
-            (std::ptr::null(), 0)
-        };
-
-        // NB: leak the func closure
-        let func: Box<Box<dyn FnMut(JsObject, T) -> NapiResult<R>>> = Box::new(Box::new(func));
-
-        extern "C" fn trampoline<T: FromJsArgs, R: NapiValueT>(
+        extern "C" fn trampoline<T: FromJsArgs, R: ToJs>(
             env: NapiEnv,
             info: napi_callback_info,
         ) -> napi_value {
-            let mut data = MaybeUninit::uninit();
-            let mut this = MaybeUninit::uninit();
-
-            let (argc, argv, this, mut func) = unsafe {
-                // NB: use this to get the number of arguments
-                // let mut argc = 0;
-                // let mut argv = [std::ptr::null_mut(); 0];
-                // api::napi_get_cb_info(
-                //     env,
-                //     info,
-                //     &mut argc,
-                //     argv.as_mut_ptr(),
-                //     std::ptr::null_mut(),
-                //     std::ptr::null_mut(),
-                // );
-
-                let mut argc = T::len();
-                let mut argv = vec![std::ptr::null_mut(); T::len()];
-                api::napi_get_cb_info(
-                    env,
-                    info,
-                    &mut argc,
-                    argv.as_mut_ptr(),
-                    this.as_mut_ptr(),
-                    data.as_mut_ptr(),
-                );
-
-                // NB: the Function maybe called multiple times, so we can should leak the
-                // closure memory here.
-                //
-                // With napi >= 5, we can add a finalizer to this function.
-                let func: &mut Box<dyn FnMut(JsObject, T) -> NapiResult<R>> =
-                    std::mem::transmute(data);
-
-                (argc, argv, this.assume_init(), func)
-            };
-
-            let args = argv
-                .into_iter()
-                .map(|arg| JsValue::from_raw(env, arg))
-                .collect();
-            let this = JsObject::from_raw(env, this);
-
-            if let Ok(args) = T::from_js_args(JsArgs(args)) {
-                napi_r!(env, =func(this, args))
-            } else {
-                env.throw_error("wrong argument type!").unwrap();
-                env.undefined().unwrap().raw()
-            }
+            crate::callback::invoke(env, || {
+                let info = CallbackInfo::from_raw(env, info);
+                let (this, args, data) = info.get(T::len())?;
+                // NB: the closure lives as long as the function
+                let func =
+                    unsafe { &mut *(data as *mut Box<dyn FnMut(JsObject, T) -> NapiResult<R>>) };
+                let args = crate::args::from_call::<T>(env, args)?;
+                Ok(func(this, args)?.to_js(env)?.raw())
+            })
         }
 
-        let fn_pointer = Box::into_raw(func) as DataPointer;
-        let value = napi_call!(
-            =napi_create_function,
+        let func: Box<dyn FnMut(JsObject, T) -> NapiResult<R>> = Box::new(func);
+        Function::create(env, name, trampoline::<T, R>, func)
+    }
+
+    /// Create a js function with a rust closure taking the `CallContext` of each call, `data`
+    /// is shared by the calls as `CallContext::data()`.
+    pub fn with_context<D, R>(
+        env: NapiEnv,
+        name: Option<impl AsRef<str>>,
+        data: D,
+        func: impl FnMut(&CallContext<D>) -> NapiResult<R> + 'static,
+    ) -> NapiResult<Function<R::Value>>
+    where
+        D: 'static,
+        R: ToJs,
+    {
+        type Closure<D, R> = (D, Box<dyn FnMut(&CallContext<D>) -> NapiResult<R>>);
+
+        extern "C" fn trampoline<D, R: ToJs>(env: NapiEnv, info: napi_callback_info) -> napi_value {
+            crate::callback::invoke(env, || {
+                let info = CallbackInfo::from_raw(env, info);
+                let (this, args, data) = info.get(crate::callback::ARGS_CAPACITY)?;
+                // NB: the closure lives as long as the function
+                let (data, func) = unsafe { &mut *(data as *mut Closure<D, R>) };
+                Ok(func(&CallContext::new(info, this, args, data))?
+                    .to_js(env)?
+                    .raw())
+            })
+        }
+
+        let closure: Closure<D, R> = (data, Box::new(func));
+        Function::create(env, name, trampoline::<D, R>, closure)
+    }
+
+    /// Create a function calling `callback` with `closure` as its data, the closure is freed
+    /// when the function is garbage-collected.
+    fn create<C>(
+        env: NapiEnv,
+        name: Option<impl AsRef<str>>,
+        callback: extern "C" fn(NapiEnv, napi_callback_info) -> napi_value,
+        closure: C,
+    ) -> NapiResult<Function<F>> {
+        let (name, len) = if let Some(name) = name.as_ref() {
+            (name.as_ref().as_ptr() as *const c_char, name.as_ref().len())
+        } else {
+            (std::ptr::null(), 0)
+        };
+
+        let fn_pointer = Box::into_raw(Box::new(closure)) as DataPointer;
+        let (status, value) = napi_call!(
+            ?napi_create_function,
             env,
             name,
             len,
-            Some(trampoline::<T, R>),
+            Some(callback),
             // pass closure to trampoline function
             fn_pointer,
         );
 
-        let mut func = Function::<R>(JsValue::from_raw(env, value), PhantomData);
-        func.gc(move |_| unsafe {
-            // NB: the leaked data is collected here.
-            let _: Box<Box<dyn FnMut(JsObject, T) -> NapiResult<R>>> =
-                Box::from_raw(fn_pointer as _);
+        let free = move || unsafe {
+            let _: Box<C> = Box::from_raw(fn_pointer as _);
+        };
+        if status.err() {
+            free();
+            return Err(status);
+        }
+
+        let mut func = Function::<F>(JsValue::from_raw(env, value), PhantomData);
+        let live = Live::new();
+        func.gc(move |_| {
+            free();
+            drop(live);
             Ok(())
         })?;
 
@@ -169,7 +176,7 @@ impl<F: NapiValueT> Function<F> {
             self.env(),
             this.raw(),
             self.raw(),
-            T::len(),
+            args.len(),
             args.as_ptr(),
         );
 
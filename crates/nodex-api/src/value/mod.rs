@@ -1,4 +1,4 @@
-use crate::{api, env::NapiEnv, prelude::*};
+use crate::{api, descriptor::Prepared, env::NapiEnv, prelude::*};
 use std::mem::MaybeUninit;
 
 #[repr(C)]
@@ -12,7 +12,11 @@ impl JsValue {
     }
 
     /// raw napi_value of this `JsValue`
+    ///
+    /// NB: in debug builds, it panics in a callback of another env.
     pub fn raw(&self) -> napi_value {
+        #[cfg(debug_assertions)]
+        crate::callback::check_env(self.0);
         self.1
     }
 
@@ -52,12 +56,15 @@ impl JsValue {
         napi_as!(self, JsArray, NapiStatus::ArrayExpected)
     }
 
+    /// NB: true for a typed_array of any element type
     pub fn is_typedarray(&self) -> NapiResult<bool> {
-        napi_is!(self, JsTypedArray)
+        Ok(napi_call!(=napi_is_typedarray, self.env(), self.raw()))
     }
-    /// view it as a typed_array, may fail if it is not a typed_array value
-    pub fn as_typedarray(&self) -> NapiResult<JsTypedArray> {
-        napi_as!(self, JsTypedArray, NapiStatus::InvalidArg)
+
+    /// view it as a typed_array of `T`, may fail if it is not a typed_array value or the
+    /// element type is not `T`
+    pub fn as_typedarray<T: TypedArrayElement>(&self) -> NapiResult<JsTypedArray<T>> {
+        napi_as!(self, JsTypedArray<T>, NapiStatus::InvalidArg)
     }
 
     pub fn is_arraybuffer(&self) -> NapiResult<bool> {
@@ -69,13 +76,13 @@ impl JsValue {
         napi_as!(self, JsArrayBuffer, NapiStatus::ArraybufferExpected)
     }
 
-    pub fn is_buffer<const N: usize>(&self) -> NapiResult<bool> {
-        napi_is!(self, JsBuffer<N>)
+    pub fn is_buffer(&self) -> NapiResult<bool> {
+        napi_is!(self, JsBuffer)
     }
 
     /// view it as a buffer, may fail if it is not a buffer value
-    pub fn as_buffer<const N: usize>(&self) -> NapiResult<JsBuffer<N>> {
-        napi_as!(self, JsBuffer<N>, NapiStatus::InvalidArg)
+    pub fn as_buffer(&self) -> NapiResult<JsBuffer> {
+        napi_as!(self, JsBuffer, NapiStatus::InvalidArg)
     }
 
     pub fn is_dataview(&self) -> NapiResult<bool> {
@@ -114,13 +121,24 @@ impl JsValue {
         napi_as!(self, JsNumber, NapiStatus::NumberExpected)
     }
 
-    pub fn is_bigint<T: Copy>(&self) -> NapiResult<bool> {
-        napi_is!(self, JsBigInt<T>)
+    pub fn is_bigint(&self) -> NapiResult<bool> {
+        napi_is!(self, JsBigInt)
     }
 
     /// view it as a bigint, may fail if it is not a bigint value
-    pub fn as_bigint<T: Copy>(&self) -> NapiResult<JsBigInt<T>> {
-        napi_as!(self, JsBigInt<T>, NapiStatus::BigintExpected)
+    pub fn as_bigint(&self) -> NapiResult<JsBigInt> {
+        napi_as!(self, JsBigInt, NapiStatus::BigintExpected)
+    }
+
+    pub fn is_promise(&self) -> NapiResult<bool> {
+        napi_is!(self, JsPromise<JsValue, JsValue>)
+    }
+
+    /// view it as a promise, may fail if it is not a promise value
+    pub fn as_promise<L: NapiValueT + Copy, R: NapiValueT + Copy>(
+        &self,
+    ) -> NapiResult<JsPromise<L, R>> {
+        napi_as!(self, JsPromise<L, R>, NapiStatus::InvalidArg)
     }
 
     pub fn is_boolean(&self) -> NapiResult<bool> {
@@ -142,6 +160,11 @@ impl JsValue {
     pub fn as_date(&self) -> NapiResult<JsDate> {
         napi_as!(self, JsDate, NapiStatus::DateExpected)
     }
+
+    /// Debug-style printer of this value, see `Pretty`.
+    pub fn pretty(&self) -> Pretty {
+        Pretty::new(*self)
+    }
 }
 
 impl NapiValueT for JsValue {
@@ -294,30 +317,46 @@ pub trait NapiValueT: NapiValueCheck + Sized {
     /// array of such property descriptors, this API will set the properties on the object one at a
     /// time, as defined by DefineOwnProperty() (described in Section 9.1.6 of the ECMA-262
     /// specification).
+    ///
+    /// The closures of the properties live as long as the functions or the object they are
+    /// defined on, see `NapiPropertyDescriptor`.
     fn define_properties<P>(&self, properties: P) -> NapiResult<()>
     where
         P: AsRef<[NapiPropertyDescriptor]>,
     {
+        let properties = Prepared::new(self.env(), properties.as_ref())?;
         napi_call!(
             napi_define_properties,
             self.env(),
             self.raw(),
-            properties.as_ref().len(),
-            properties.as_ref().as_ptr() as *const _,
-        )
+            properties.raw().len(),
+            properties.raw().as_ptr(),
+        );
+        properties.keep_alive(self.value())
     }
 
-    /// This is a hook which is fired when the value is gabage-collected.
+    /// This is a hook which is fired when the value is gabage-collected, it can be added
+    /// multiple times.
     /// For napi >= 5, we use napi_add_finalizer,
-    /// For napi < 5, we use napi_wrap.
-    fn gc<Finalizer>(&mut self, finalizer: Finalizer) -> NapiResult<NapiRef>
+    /// For napi < 5, we use napi_wrap on a carrier object, which is only referenced by a hidden
+    /// property of this value, so it is collected together with this value.
+    fn gc<Finalizer>(&mut self, finalizer: Finalizer) -> NapiResult<()>
     where
         Finalizer: FnOnce(NapiEnv) -> NapiResult<()>,
     {
         #[cfg(feature = "v5")]
         return self.finalizer(finalizer);
         #[cfg(not(feature = "v5"))]
-        return self.wrap((), move |env, _| finalizer(env));
+        {
+            // NB: napi_wrap can only be called once on an object, and this value may be
+            // wrapped by the user.
+            let mut carrier = JsObject::new(self.env())?;
+            carrier.wrap((), move |env, _| finalizer(env))?;
+            self.define_properties([DescriptorValueBuilder::new()
+                .with_name(JsSymbol::new(self.env())?)
+                .with_value(carrier)
+                .build()?])
+        }
     }
 
     #[cfg(feature = "v5")]
@@ -331,12 +370,9 @@ pub trait NapiValueT: NapiValueCheck + Sized {
     /// each of them to the JavaScript object, and
     /// * the object manipulated by the API can be used with napi_wrap().
     ///
-    /// Caution: The optional returned reference (if obtained) should be deleted via
-    /// napi_delete_reference ONLY in response to the finalize callback invocation.
-    /// If it is deleted before then, then the finalize callback may never be invoked.
-    /// herefore, when obtaining a reference a finalize callback is also required in order
-    /// to enable correct disposal of the reference.
-    fn finalizer<Finalizer>(&self, finalizer: Finalizer) -> NapiResult<NapiRef>
+    /// NB: no reference is requested, deleting it before the finalizer runs would cancel the
+    /// finalizer. Use `Weak` to observe the object instead.
+    fn finalizer<Finalizer>(&self, finalizer: Finalizer) -> NapiResult<()>
     where
         Finalizer: FnOnce(NapiEnv) -> NapiResult<()>,
     {
@@ -351,23 +387,27 @@ pub trait NapiValueT: NapiValueCheck + Sized {
             // NB: here we collect the memory of finalizer closure
             let finalizer: Box<Box<dyn FnOnce(NapiEnv) -> NapiResult<()>>> =
                 Box::from_raw(finalizer as _);
-            if let Err(err) = finalizer(env) {
-                log::error!("NapiValueT::finalizer(): {}", err);
-            }
+            crate::callback::guard("NapiValueT::finalizer()", || finalizer(env));
         }
 
         let finalizer: Box<Box<dyn FnOnce(NapiEnv) -> NapiResult<()>>> =
             Box::new(Box::new(finalizer));
-        let reference = napi_call!(
-            =napi_add_finalizer,
-            self.env(),
-            self.raw(),
-            std::ptr::null_mut(),
-            Some(finalizer_trampoline),
-            Box::into_raw(finalizer) as DataPointer,
-        );
-
-        Ok(NapiRef::from_raw(self.env(), reference))
+        let finalizer = Box::into_raw(finalizer);
+        let status = unsafe {
+            api::napi_add_finalizer(
+                self.env(),
+                self.raw(),
+                std::ptr::null_mut(),
+                Some(finalizer_trampoline),
+                finalizer as DataPointer,
+                std::ptr::null_mut(),
+            )
+        };
+        if status.err() {
+            unsafe { drop(Box::from_raw(finalizer)) };
+            return Err(status);
+        }
+        Ok(())
     }
 
     #[allow(clippy::type_complexity)]
@@ -384,22 +424,20 @@ pub trait NapiValueT: NapiValueCheck + Sized {
     /// Typically when wrapping a class instance, a finalize callback should be provided that simply
     /// deletes the native instance that is received as the data argument to the finalize callback.
     ///
-    /// The optional returned reference is initially a weak reference, meaning it has a reference
-    /// count of 0. Typically this reference count would be incremented temporarily during async
-    /// operations that require the instance to remain valid.
-    ///
-    /// Caution: The optional returned reference (if obtained) should be deleted via napi_delete_reference
-    /// ONLY in response to the finalize callback invocation. If it is deleted before then, then
-    /// the finalize callback may never be invoked. Therefore, when obtaining a reference a finalize
-    /// callback is also required in order to enable correct disposal of the reference.
+    /// NB: no reference is requested, deleting it before the finalizer runs would cancel the
+    /// finalizer. Use `Weak` to observe the object instead.
     ///
     /// Calling napi_wrap() a second time on an object will return an error. To associate another
     /// native instance with the object, use napi_remove_wrap() first.
+    ///
+    /// NB: the object is tagged with the type tag of `T` once it is wrapped, see `nodex::tag`. A
+    /// tag can not be removed, so an object only wraps instances of one type, wrapping another
+    /// type returns napi_invalid_arg.
     fn wrap<T>(
         &mut self,
         data: T,
         finalizer: impl FnOnce(NapiEnv, T) -> NapiResult<()>,
-    ) -> NapiResult<NapiRef> {
+    ) -> NapiResult<()> {
         // NB: Because we add a closure to the napi finalizer, it's better
         // to **CAPTURE** the leaked data from rust side, so here we just
         // ignore the passed in native data pointer.
@@ -412,23 +450,45 @@ pub trait NapiValueT: NapiValueCheck + Sized {
             let finalizer: Box<Box<dyn FnOnce(NapiEnv, T) -> NapiResult<()>>> =
                 Box::from_raw(finalizer as _);
             let data = Box::<T>::from_raw(data as _);
-            if let Err(err) = finalizer(env, *data) {
-                log::error!("NapiValueT::wrap(): {}", err);
-            }
+            crate::callback::guard("NapiValueT::wrap()", || finalizer(env, *data));
         }
 
         let finalizer: Box<Box<dyn FnOnce(NapiEnv, T) -> NapiResult<()>>> =
             Box::new(Box::new(finalizer));
-        let reference = napi_call!(
-            =napi_wrap,
-            self.env(),
-            self.raw(),
-            Box::into_raw(Box::new(data)) as DataPointer,
-            Some(finalizer_trampoline::<T>),
-            Box::into_raw(finalizer) as DataPointer,
-        );
+        let finalizer = Box::into_raw(finalizer);
+        let data = Box::into_raw(Box::new(data));
+        let status = unsafe {
+            api::napi_wrap(
+                self.env(),
+                self.raw(),
+                data as DataPointer,
+                Some(finalizer_trampoline::<T>),
+                finalizer as DataPointer,
+                std::ptr::null_mut(),
+            )
+        };
+        if status.err() {
+            unsafe {
+                drop(Box::from_raw(finalizer));
+                drop(Box::from_raw(data));
+            }
+            return Err(status);
+        }
 
-        Ok(NapiRef::from_raw(self.env(), reference))
+        // NB: the tag is permanent, so the object is tagged once the wrap succeeds, and the wrap
+        // is rolled back if the object is tagged with another type
+        if let Err(status) = crate::tag::tag_object(self.value(), &NapiTypeTag::of::<T>()) {
+            let (removed, _) = napi_call!(?napi_remove_wrap, self.env(), self.raw());
+            // NB: the finalizer is not called once the wrap is removed
+            if removed.ok() {
+                unsafe {
+                    drop(Box::from_raw(finalizer));
+                    drop(Box::from_raw(data));
+                }
+            }
+            return Err(status);
+        }
+        Ok(())
     }
 
     /// Retrieves a native instance that was previously wrapped in a JavaScript object using
@@ -440,21 +500,31 @@ pub trait NapiValueT: NapiValueCheck + Sized {
     /// target of the call can be obtained then by calling napi_unwrap() on the wrapper object.
     ///
     /// NB: if a there is no wrap or the wrap is just removed by NapiValue::remove_wrap, return
-    /// None.
-    fn unwrap<T>(&self) -> NapiResult<Option<&mut T>> {
-        let (status, value) = napi_call!(?napi_unwrap, self.env(), self.raw());
-        match status {
-            NapiStatus::Ok => unsafe { Ok(Some(&mut *(value as *mut T))) },
-            NapiStatus::InvalidArg => Ok(None),
-            err => Err(err),
-        }
+    /// None. If the object is not tagged with the type tag of `T`, e.g. it wraps another type,
+    /// `TypeTagError::Mismatch` is returned.
+    ///
+    /// NB: nothing prevents two calls from holding `&mut T` to the same instance, e.g. a method
+    /// calling into js which calls another method of the object. Use `Wrapped<T>` to track the
+    /// borrows, the objects wrapped by `Wrapped` and `WrappedSync` are refused with
+    /// `TypeTagError::Mismatch`.
+    fn unwrap<T>(&self) -> Result<Option<&mut T>, TypeTagError> {
+        Ok(unwrap_raw::<T>(self.value())?.map(|value| unsafe { &mut *value }))
     }
 
     /// Retrieves a native instance that was previously wrapped in the JavaScript object js_object
     /// using napi_wrap() and removes the wrapping. If a finalize callback was associated with the
     /// wrapping, it will no longer be called when the JavaScript object becomes garbage-collected.
-    fn remove_wrap<T>(&mut self) -> NapiResult<T> {
-        let value = napi_call!(=napi_remove_wrap, self.env(), self.raw());
+    ///
+    /// NB: the wrap is kept if the object is not tagged with the type tag of `T`, e.g. the
+    /// objects wrapped by `Wrapped` and `WrappedSync` are refused with `TypeTagError::Mismatch`.
+    fn remove_wrap<T>(&mut self) -> Result<T, TypeTagError> {
+        if !crate::tag::check_object(self.value(), &NapiTypeTag::of::<T>())? {
+            return Err(TypeTagError::mismatch::<T>());
+        }
+        let (status, value) = napi_call!(?napi_remove_wrap, self.env(), self.raw());
+        if status.err() {
+            return Err(status.into());
+        }
         unsafe {
             let value: Box<T> = Box::from_raw(value as *mut _);
             Ok(*value)
@@ -479,12 +549,27 @@ pub trait NapiValueT: NapiValueCheck + Sized {
     }
 }
 
+/// The native instance wrapped in `value`, checked against the type tag of `T`.
+pub(crate) fn unwrap_raw<T>(value: JsValue) -> Result<Option<*mut T>, TypeTagError> {
+    let (status, data) = napi_call!(?napi_unwrap, value.env(), value.raw());
+    match status {
+        NapiStatus::Ok => {
+            if !crate::tag::check_object(value, &NapiTypeTag::of::<T>())? {
+                return Err(TypeTagError::mismatch::<T>());
+            }
+            Ok(Some(data as *mut T))
+        }
+        NapiStatus::InvalidArg => Ok(None),
+        err => Err(err.into()),
+    }
+}
+
 mod array;
 mod arraybuffer;
 mod bigint;
 mod boolean;
 mod buffer;
-mod class;
+pub(crate) mod class;
 mod dataview;
 mod date;
 mod error;
@@ -495,16 +580,18 @@ mod name;
 mod null;
 mod number;
 mod object;
+mod pretty;
 mod promise;
 mod typedarray;
 mod undefined;
+mod wrapped;
 
 pub use array::JsArray;
 pub use arraybuffer::JsArrayBuffer;
 pub use bigint::JsBigInt;
 pub use boolean::JsBoolean;
 pub use buffer::JsBuffer;
-pub use class::JsClass;
+pub use class::{JsClass, NapiClass, NapiClassMethods, NapiClassRef};
 pub use dataview::JsDataView;
 pub use date::JsDate;
 pub use error::JsError;
@@ -515,6 +602,12 @@ pub use name::{JsString, JsSymbol};
 pub use null::JsNull;
 pub use number::JsNumber;
 pub use object::JsObject;
+#[cfg(feature = "v6")]
+pub use object::{Entries, Keys, Values};
+pub use pretty::Pretty;
 pub use promise::JsPromise;
-pub use typedarray::JsTypedArray;
+#[cfg(feature = "v4")]
+pub use promise::{JsFuture, PromiseError};
+pub use typedarray::{JsTypedArray, TypedArrayElement, Uint8Clamped};
 pub use undefined::JsUndefined;
+pub use wrapped::{Wrapped, WrappedSync};
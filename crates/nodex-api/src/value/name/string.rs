@@ -35,34 +35,152 @@ impl JsString {
         Ok(JsString(JsValue::from_raw(env, value)))
     }
 
+    /// This API creates a JavaScript string value from an ISO-8859-1-encoded C string. The
+    /// native string is copied.
+    pub fn latin1(env: NapiEnv, value: impl AsRef<[u8]>) -> NapiResult<JsString> {
+        let value = napi_call!(
+            =napi_create_string_latin1,
+            env,
+            value.as_ref().as_ptr() as *const _,
+            value.as_ref().len(),
+        );
+
+        Ok(JsString(JsValue::from_raw(env, value)))
+    }
+
+    /// This API creates a JavaScript string value from a UTF16-LE-encoded C string. The native
+    /// string is copied.
+    pub fn utf16(env: NapiEnv, value: impl AsRef<[u16]>) -> NapiResult<JsString> {
+        let value = napi_call!(
+            =napi_create_string_utf16,
+            env,
+            value.as_ref().as_ptr(),
+            value.as_ref().len(),
+        );
+
+        Ok(JsString(JsValue::from_raw(env, value)))
+    }
+
+    /// The length in bytes of the UTF8-encoded string.
+    pub fn len_utf8(&self) -> NapiResult<usize> {
+        self.len_with(api::napi_get_value_string_utf8)
+    }
+
+    /// The length in code units of the UTF16-encoded string, which is the `length` of the string
+    /// in JavaScript. It is the length of the ISO-8859-1-encoded string as well.
+    pub fn len_utf16(&self) -> NapiResult<usize> {
+        self.len_with(api::napi_get_value_string_utf16)
+    }
+
     /// This API returns the UTF8-encoded string corresponding the value passed in.
     pub fn get_utf8(&self) -> NapiResult<String> {
-        let size = napi_call!(
-            =napi_get_value_string_utf8,
-            self.env(),
-            self.raw(),
-            std::ptr::null_mut(),
-            0,
-        );
+        let mut buffer = vec![0u8; self.len_utf8()? + 1];
+        let len = self.write_with(
+            api::napi_get_value_string_utf8,
+            buffer.as_mut_ptr() as *mut _,
+            buffer.len(),
+        )?;
+        buffer.truncate(len);
+        String::from_utf8(buffer).map_err(|_| NapiStatus::StringExpected)
+    }
 
-        let mut buffer = vec![0u8; size + 1];
-        let size = napi_call!(
-            =napi_get_value_string_utf8,
-            self.env(),
-            self.raw(),
+    /// This API returns the ISO-8859-1-encoded string corresponding the value passed in,
+    /// characters out of ISO-8859-1 are truncated to their lower byte.
+    pub fn get_latin1(&self) -> NapiResult<Vec<u8>> {
+        let mut buffer = vec![0u8; self.len_utf16()? + 1];
+        let len = self.write_with(
+            api::napi_get_value_string_latin1,
             buffer.as_mut_ptr() as *mut _,
-            // should contains the NULL terminator
-            size + 1,
-        );
+            buffer.len(),
+        )?;
+        buffer.truncate(len);
+        Ok(buffer)
+    }
 
-        unsafe {
-            // remove trailing NULL
-            buffer.set_len(size);
-            Ok(String::from_utf8_unchecked(buffer))
+    /// This API returns the UTF16-encoded string corresponding the value passed in.
+    pub fn get_utf16(&self) -> NapiResult<Vec<u16>> {
+        let mut buffer = vec![0u16; self.len_utf16()? + 1];
+        let len = self.write_with(
+            api::napi_get_value_string_utf16,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+        )?;
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+
+    /// Get the UTF8-encoded string, borrowed from `buffer` if it fits in, otherwise a new
+    /// String is allocated.
+    ///
+    /// NB: the buffer should have room for the NULL terminator.
+    pub fn get_str<'b>(&self, buffer: &'b mut [u8]) -> NapiResult<Cow<'b, str>> {
+        match self.write_utf8(buffer)? {
+            (len, false) => std::str::from_utf8(&buffer[..len])
+                .map(Cow::Borrowed)
+                .map_err(|_| NapiStatus::StringExpected),
+            (_, true) => self.get_utf8().map(Cow::Owned),
         }
     }
+
+    /// Write the UTF8-encoded string into `buffer`, returns the number of bytes copied and
+    /// whether the string was truncated.
+    ///
+    /// NB: the string is NULL terminated, so at most `buffer.len() - 1` bytes are copied, and
+    /// characters are never split.
+    pub fn write_utf8(&self, buffer: &mut [u8]) -> NapiResult<(usize, bool)> {
+        let len = self.write_with(
+            api::napi_get_value_string_utf8,
+            buffer.as_mut_ptr() as *mut _,
+            buffer.len(),
+        )?;
+        Ok((len, len < self.len_utf8()?))
+    }
+
+    /// Write the ISO-8859-1-encoded string into `buffer`, returns the number of bytes copied
+    /// and whether the string was truncated.
+    ///
+    /// NB: the string is NULL terminated, so at most `buffer.len() - 1` bytes are copied.
+    pub fn write_latin1(&self, buffer: &mut [u8]) -> NapiResult<(usize, bool)> {
+        let len = self.write_with(
+            api::napi_get_value_string_latin1,
+            buffer.as_mut_ptr() as *mut _,
+            buffer.len(),
+        )?;
+        Ok((len, len < self.len_utf16()?))
+    }
+
+    /// Write the UTF16-encoded string into `buffer`, returns the number of code units copied
+    /// and whether the string was truncated.
+    ///
+    /// NB: the string is NULL terminated, so at most `buffer.len() - 1` code units are copied.
+    pub fn write_utf16(&self, buffer: &mut [u16]) -> NapiResult<(usize, bool)> {
+        let len = self.write_with(
+            api::napi_get_value_string_utf16,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+        )?;
+        Ok((len, len < self.len_utf16()?))
+    }
+
+    /// If buf is NULL, the string getters return the length of the string.
+    fn len_with<C>(&self, get: StringGetter<C>) -> NapiResult<usize> {
+        self.write_with(get, std::ptr::null_mut(), 0)
+    }
+
+    fn write_with<C>(&self, get: StringGetter<C>, buf: *mut C, size: usize) -> NapiResult<usize> {
+        let mut result = MaybeUninit::uninit();
+        let status = unsafe { get(self.env(), self.raw(), buf, size, result.as_mut_ptr()) };
+        if status.err() {
+            return Err(status);
+        }
+        unsafe { Ok(result.assume_init()) }
+    }
 }
 
+/// napi_get_value_string_{latin1,utf8,utf16}
+type StringGetter<C> =
+    unsafe extern "C" fn(NapiEnv, napi_value, *mut C, usize, *mut usize) -> NapiStatus;
+
 napi_value_t!(JsString);
 
 impl From<JsString> for String {
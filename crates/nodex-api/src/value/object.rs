@@ -57,6 +57,88 @@ impl JsObject {
         Ok(JsArray::from_raw(self.env(), names))
     }
 
+    /// This API returns the prototype of this object, None if the prototype is null, e.g. the
+    /// object is created by `Object.create(null)`.
+    pub fn prototype(&self) -> NapiResult<Option<JsObject>> {
+        let prototype = napi_call!(=napi_get_prototype, self.env(), self.raw());
+        let prototype = JsValue::from_raw(self.env(), prototype);
+        if prototype.kind()? == NapiValuetype::Null {
+            Ok(None)
+        } else {
+            Ok(Some(JsObject::from_value(prototype)))
+        }
+    }
+
+    /// Set the prototype of this object by `Object.setPrototypeOf()`, None sets it to null.
+    pub fn set_prototype(&mut self, prototype: Option<JsObject>) -> NapiResult<()> {
+        let env = self.env();
+        // NB: the Object constructor is a function
+        let object = env
+            .global()?
+            .object()
+            .get::<JsFunction>("Object")?
+            .coerce_to_object()?;
+        let set_prototype_of: JsFunction = object.get("setPrototypeOf")?;
+        let prototype = match prototype {
+            Some(prototype) => prototype.value(),
+            None => env.null()?.value(),
+        };
+        set_prototype_of.call(object, (*self, prototype))?;
+        Ok(())
+    }
+
+    #[cfg(feature = "v6")]
+    /// Iterate over the own enumerable string-keyed properties of this object, like
+    /// `Object.entries()`.
+    pub fn entries(&self) -> NapiResult<Entries> {
+        self.entries_with(
+            NapiKeyCollectionMode::KeyOwnOnly,
+            NapiKeyFilter::Enumerable | NapiKeyFilter::SkipSymbols,
+            NapiKeyConversion::NumbersToStrings,
+        )
+    }
+
+    #[cfg(feature = "v6")]
+    /// Iterate over the properties of this object selected by `mode` and `filter`.
+    pub fn entries_with(
+        &self,
+        mode: NapiKeyCollectionMode,
+        filter: NapiKeyFilter,
+        conversion: NapiKeyConversion,
+    ) -> NapiResult<Entries> {
+        let keys = self.get_all_property_names(mode, filter, conversion)?;
+        Ok(Entries {
+            object: *self,
+            keys,
+            index: 0,
+            len: keys.len()?,
+        })
+    }
+
+    #[cfg(feature = "v6")]
+    /// Iterate over the own enumerable string keys of this object, like `Object.keys()`.
+    pub fn keys(&self) -> NapiResult<Keys> {
+        Ok(Keys(self.entries()?))
+    }
+
+    #[cfg(feature = "v6")]
+    /// Iterate over the values of the own enumerable string-keyed properties of this object, like
+    /// `Object.values()`.
+    pub fn values(&self) -> NapiResult<Values> {
+        Ok(Values(self.entries()?))
+    }
+
+    #[cfg(feature = "v6")]
+    /// Iterate over all own keys of this object, including the non-enumerable and the symbol
+    /// ones, like `Reflect.ownKeys()`.
+    pub fn own_keys(&self) -> NapiResult<Keys> {
+        Ok(Keys(self.entries_with(
+            NapiKeyCollectionMode::KeyOwnOnly,
+            NapiKeyFilter::KeyAllProperties,
+            NapiKeyConversion::NumbersToStrings,
+        )?))
+    }
+
     /// Set value by string-like key.
     pub fn set<T: NapiValueT>(&mut self, key: impl AsRef<str>, value: T) -> NapiResult<()> {
         let name = self.env().string(key.as_ref())?;
@@ -201,6 +283,81 @@ impl JsObject {
 
 napi_value_t!(JsObject);
 
+#[cfg(feature = "v6")]
+/// Iterator over the properties of an object, yields `(key, value)`.
+///
+/// The keys are collected when the iterator is created, a property deleted afterwards yields
+/// undefined.
+#[derive(Clone, Debug)]
+pub struct Entries {
+    object: JsObject,
+    keys: JsArray,
+    index: u32,
+    len: u32,
+}
+
+#[cfg(feature = "v6")]
+impl Entries {
+    fn next_key(&mut self) -> Option<NapiResult<JsValue>> {
+        if self.index >= self.len {
+            return None;
+        }
+        self.index += 1;
+        Some(self.keys.get(self.index - 1))
+    }
+}
+
+#[cfg(feature = "v6")]
+impl Iterator for Entries {
+    type Item = NapiResult<(JsValue, JsValue)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.next_key()?;
+        Some(key.and_then(|key| Ok((key, self.object.get_property(key)?))))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.len - self.index) as usize;
+        (len, Some(len))
+    }
+}
+
+#[cfg(feature = "v6")]
+/// Iterator over the keys of an object.
+#[derive(Clone, Debug)]
+pub struct Keys(Entries);
+
+#[cfg(feature = "v6")]
+impl Iterator for Keys {
+    type Item = NapiResult<JsValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_key()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+#[cfg(feature = "v6")]
+/// Iterator over the values of an object.
+#[derive(Clone, Debug)]
+pub struct Values(Entries);
+
+#[cfg(feature = "v6")]
+impl Iterator for Values {
+    type Item = NapiResult<JsValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.0.next()?.map(|(_, value)| value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
 impl NapiValueCheck for JsObject {
     fn check(&self) -> NapiResult<bool> {
         Ok(self.kind()? == NapiValuetype::Object)
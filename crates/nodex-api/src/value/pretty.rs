@@ -0,0 +1,267 @@
+use crate::{api, prelude::*};
+use std::fmt::{self, Write};
+
+/// Debug-style printer of a js value and its properties, created by `JsValue::pretty()`.
+///
+/// Objects nested deeper than `depth` are printed as `[Object]` or `[Array]`, and an object
+/// which contains itself is printed as `[Circular]`. Printing never leaves an exception pending,
+/// e.g. a throwing getter is printed as `<napi error: ...>`.
+///
+/// It prints the same by `Display` and `Debug`, so a `Pretty` can be kept in a struct deriving
+/// `Debug`, or passed to `dbg!`.
+///
+/// ```ignore
+/// fn check(value: JsValue) -> NapiResult<()> {
+///     if !value.is_object()? {
+///         log::error!("unexpected argument: {}", value.pretty().depth(3));
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Copy, Clone)]
+pub struct Pretty {
+    value: JsValue,
+    depth: usize,
+    max_items: usize,
+}
+
+impl Pretty {
+    pub(crate) fn new(value: JsValue) -> Pretty {
+        Pretty {
+            value,
+            depth: 2,
+            max_items: 100,
+        }
+    }
+
+    /// The depth of nested objects to print, 2 by default.
+    pub fn depth(mut self, depth: usize) -> Pretty {
+        self.depth = depth;
+        self
+    }
+
+    /// The number of properties to print of each object, 100 by default.
+    pub fn max_items(mut self, max_items: usize) -> Pretty {
+        self.max_items = max_items;
+        self
+    }
+
+    /// Print the value, the error of printing is printed in place.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        if let Err(status) = self.print(&mut out, self.value, 0, &mut vec![]) {
+            recover(self.value.env(), &mut out, status);
+        }
+        out
+    }
+
+    fn print(
+        &self,
+        out: &mut String,
+        value: JsValue,
+        level: usize,
+        seen: &mut Vec<JsValue>,
+    ) -> NapiResult<()> {
+        match value.kind()? {
+            NapiValuetype::Undefined => out.push_str("undefined"),
+            NapiValuetype::Null => out.push_str("null"),
+            NapiValuetype::Boolean => push(out, value.as_boolean()?.get()?),
+            NapiValuetype::Number => {
+                let number = value.as_number()?.get_value_double()?;
+                if number.is_infinite() {
+                    out.push_str(if number > 0. { "Infinity" } else { "-Infinity" });
+                } else {
+                    push(out, number);
+                }
+            }
+            NapiValuetype::String => {
+                let _ = write!(out, "{:?}", value.as_string()?.get()?);
+            }
+            NapiValuetype::Bigint => {
+                push(out, value.coerce_to_string()?.get()?);
+                out.push('n');
+            }
+            NapiValuetype::Symbol => {
+                let description = value
+                    .coerce_to_object()?
+                    .get_named_property("description")?;
+                out.push_str("Symbol(");
+                if description.kind()? == NapiValuetype::String {
+                    push(out, description.as_string()?.get()?);
+                }
+                out.push(')');
+            }
+            NapiValuetype::External => out.push_str("[External]"),
+            NapiValuetype::Function => {
+                let name = name(value)?;
+                if name.is_empty() {
+                    out.push_str("[Function (anonymous)]");
+                } else {
+                    let _ = write!(out, "[Function: {}]", name);
+                }
+            }
+            NapiValuetype::Object => {
+                for ancestor in seen.iter() {
+                    if ancestor.equals(value)? {
+                        out.push_str("[Circular]");
+                        return Ok(());
+                    }
+                }
+
+                seen.push(value);
+                let result = self.print_object(out, value, level, seen);
+                seen.pop();
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_object(
+        &self,
+        out: &mut String,
+        value: JsValue,
+        level: usize,
+        seen: &mut Vec<JsValue>,
+    ) -> NapiResult<()> {
+        if unsafe { value.cast::<JsError>() }.check()? {
+            let _ = write!(out, "[{}]", value.coerce_to_string()?.get()?);
+            return Ok(());
+        }
+
+        #[cfg(feature = "v5")]
+        if value.is_date()? {
+            push(out, value.coerce_to_string()?.get()?);
+            return Ok(());
+        }
+
+        if value.is_typedarray()? {
+            let mut length = 0;
+            napi_call!(
+                napi_get_typedarray_info,
+                value.env(),
+                value.raw(),
+                std::ptr::null_mut(),
+                &mut length,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            let _ = write!(out, "{}({})", name(constructor(value)?)?, length);
+            return Ok(());
+        }
+
+        if value.is_arraybuffer()? {
+            let length = value.as_arraybuffer()?.buffer()?.len();
+            let _ = write!(out, "ArrayBuffer({})", length);
+            return Ok(());
+        }
+
+        if value.is_array()? {
+            if level >= self.depth {
+                out.push_str("[Array]");
+                return Ok(());
+            }
+
+            let array = value.as_array()?;
+            let len = array.len()?;
+            out.push('[');
+            for index in 0..len.min(self.max_items as u32) {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                self.print(out, array.get(index)?, level + 1, seen)?;
+            }
+            if len as usize > self.max_items {
+                let _ = write!(out, ", ... {} more items", len as usize - self.max_items);
+            }
+            out.push(']');
+            return Ok(());
+        }
+
+        let object = value.as_object()?;
+        let class = name(constructor(value)?)?;
+        if !class.is_empty() && class != "Object" {
+            let _ = write!(out, "{} ", class);
+        }
+
+        if level >= self.depth {
+            out.push_str("[Object]");
+            return Ok(());
+        }
+
+        #[cfg(feature = "v6")]
+        let keys = object.keys()?.collect::<NapiResult<Vec<_>>>()?;
+        #[cfg(not(feature = "v6"))]
+        let keys = {
+            let names = object.get_property_names()?;
+            (0..names.len()?)
+                .map(|index| names.get(index))
+                .collect::<NapiResult<Vec<_>>>()?
+        };
+
+        if keys.is_empty() {
+            out.push_str("{}");
+            return Ok(());
+        }
+
+        out.push_str("{ ");
+        for (index, key) in keys.iter().take(self.max_items).enumerate() {
+            if index > 0 {
+                out.push_str(", ");
+            }
+            let _ = write!(out, "{}: ", key.coerce_to_string()?.get()?);
+            match object.get_property(*key) {
+                Ok(value) => self.print(out, value, level + 1, seen)?,
+                Err(status) => recover(value.env(), out, status),
+            }
+        }
+        if keys.len() > self.max_items {
+            let _ = write!(out, ", ... {} more items", keys.len() - self.max_items);
+        }
+        out.push_str(" }");
+        Ok(())
+    }
+}
+
+fn push(out: &mut String, value: impl fmt::Display) {
+    let _ = write!(out, "{}", value);
+}
+
+/// The `constructor` of an object, undefined for objects without prototype.
+fn constructor(value: JsValue) -> NapiResult<JsValue> {
+    value.as_object()?.get_named_property("constructor")
+}
+
+/// The `name` of a function, empty if it is not a function or the name is not a string.
+fn name(value: JsValue) -> NapiResult<String> {
+    if value.kind()? != NapiValuetype::Function {
+        return Ok(String::new());
+    }
+    let name = value.coerce_to_object()?.get_named_property("name")?;
+    if name.kind()? == NapiValuetype::String {
+        name.as_string()?.get()
+    } else {
+        Ok(String::new())
+    }
+}
+
+/// Print the error in place, and swallow the exception thrown by e.g. a getter.
+fn recover(env: NapiEnv, out: &mut String, status: NapiStatus) {
+    if let Ok(true) = env.is_exception_pending() {
+        let _ = env.get_and_clear_last_exception();
+    }
+    let _ = write!(out, "<{}>", status);
+}
+
+impl fmt::Display for Pretty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+impl fmt::Debug for Pretty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
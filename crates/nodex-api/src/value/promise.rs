@@ -1,5 +1,15 @@
 use crate::{api, prelude::*};
-use std::{marker::PhantomData, mem::MaybeUninit, os::raw::c_char};
+use std::{
+    cell::Cell,
+    future::Future,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    os::raw::c_char,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
 
 #[derive(Debug, Copy, Clone)]
 pub struct JsPromise<L: NapiValueT, R: NapiValueT>(
@@ -10,22 +20,10 @@ pub struct JsPromise<L: NapiValueT, R: NapiValueT>(
 );
 
 impl<L: NapiValueT + Copy, R: NapiValueT + Copy> JsPromise<L, R> {
-    pub(crate) fn from_raw(value: JsValue, deferred: napi_deferred) -> JsPromise<L, R> {
+    pub(crate) fn from_deferred(value: JsValue, deferred: napi_deferred) -> JsPromise<L, R> {
         JsPromise(value, deferred, PhantomData, PhantomData)
     }
 
-    pub fn env(&self) -> NapiEnv {
-        self.0.env()
-    }
-
-    pub fn raw(&self) -> napi_value {
-        self.0.raw()
-    }
-
-    pub fn value(&self) -> JsValue {
-        self.0
-    }
-
     /// This API creates a deferred object and a JavaScript promise.
     pub fn new(env: NapiEnv) -> NapiResult<JsPromise<L, R>> {
         let mut deferred = MaybeUninit::uninit();
@@ -38,7 +36,7 @@ impl<L: NapiValueT + Copy, R: NapiValueT + Copy> JsPromise<L, R> {
 
         let deferred = unsafe { deferred.assume_init() };
 
-        Ok(Self::from_raw(JsValue(env, promise), deferred))
+        Ok(Self::from_deferred(JsValue(env, promise), deferred))
     }
 
     /// This API resolves a JavaScript promise by way of the deferred object with which it is
@@ -48,7 +46,13 @@ impl<L: NapiValueT + Copy, R: NapiValueT + Copy> JsPromise<L, R> {
     /// call must have been retained in order to be passed to this API.
     ///
     /// The deferred object is freed upon successful completion.
+    ///
+    /// NB: only the promises created by JsPromise::new() can be resolved, napi_invalid_arg is
+    /// returned otherwise.
     pub fn resolve(&self, resolution: L) -> NapiResult<()> {
+        if self.1.is_null() {
+            return Err(NapiStatus::InvalidArg);
+        }
         napi_call!(napi_resolve_deferred, self.env(), self.1, resolution.raw())
     }
 
@@ -59,7 +63,13 @@ impl<L: NapiValueT + Copy, R: NapiValueT + Copy> JsPromise<L, R> {
     /// from that call must have been retained in order to be passed to this API.
     ///
     /// The deferred object is freed upon successful completion.
+    ///
+    /// NB: only the promises created by JsPromise::new() can be rejected, napi_invalid_arg is
+    /// returned otherwise.
     pub fn reject(&self, rejection: R) -> NapiResult<()> {
+        if self.1.is_null() {
+            return Err(NapiStatus::InvalidArg);
+        }
         napi_call!(napi_reject_deferred, self.env(), self.1, rejection.raw())
     }
 }
@@ -88,6 +98,217 @@ impl<L: NapiValueT + Copy + 'static, R: NapiValueT + Copy + 'static> JsPromise<L
     }
 }
 
+impl<L: NapiValueT + Copy + 'static, R: NapiValueT + Copy + 'static> JsPromise<L, R> {
+    /// Call `then` of this promise, `on_fulfilled` or `on_rejected` is called on the js thread
+    /// when the promise is settled. It works for any thenable.
+    pub fn then(
+        &self,
+        on_fulfilled: impl FnOnce(L) -> NapiResult<()> + 'static,
+        on_rejected: impl FnOnce(R) -> NapiResult<()> + 'static,
+    ) -> NapiResult<()> {
+        self.then_value(
+            move |value| on_fulfilled(value.cast_checked()?),
+            move |reason| on_rejected(reason.cast_checked()?),
+        )
+    }
+
+    /// Call `then` of this promise with handlers taking the settled value as is.
+    fn then_value(
+        &self,
+        on_fulfilled: impl FnOnce(JsValue) -> NapiResult<()> + 'static,
+        on_rejected: impl FnOnce(JsValue) -> NapiResult<()> + 'static,
+    ) -> NapiResult<()> {
+        let env = self.env();
+        let then: JsFunction = self.0.as_object()?.get("then")?;
+
+        let mut on_fulfilled = Some(on_fulfilled);
+        let on_fulfilled = env.func(move |_, value: JsValue| {
+            if let Some(on_fulfilled) = on_fulfilled.take() {
+                on_fulfilled(value)?;
+            }
+            value.env().undefined()
+        })?;
+
+        let mut on_rejected = Some(on_rejected);
+        let on_rejected = env.func(move |_, reason: JsValue| {
+            if let Some(on_rejected) = on_rejected.take() {
+                on_rejected(reason)?;
+            }
+            reason.env().undefined()
+        })?;
+
+        then.call(self.0.as_object()?, (on_fulfilled, on_rejected))?;
+        Ok(())
+    }
+
+    #[cfg(feature = "v4")]
+    /// Convert this promise to a rust future, which can be awaited on any thread. The fulfilled
+    /// value is converted by `FromJs` on the js thread, and delivered through a threadsafe
+    /// function.
+    ///
+    /// NB: the future must not be blocked on in the js thread, which would deadlock.
+    pub fn into_future<T>(&self) -> NapiResult<JsFuture<T>>
+    where
+        T: FromJs + Send + 'static,
+    {
+        let env = self.env();
+        let shared = Arc::new(Mutex::new(Shared::<T> {
+            result: None,
+            waker: None,
+        }));
+
+        let settled = shared.clone();
+        let closing = shared.clone();
+        let future = JsFuture(shared.clone());
+        let tsfn: NapiTsfn<Result<T, PromiseError>> = NapiTsfn::new(
+            env,
+            "nodex-promise-future",
+            env.func(|this, ()| this.env().undefined())?,
+            // NB: the env is torn down before the promise is settled
+            move |_| {
+                closing
+                    .lock()
+                    .unwrap()
+                    .settle(Err(PromiseError::Napi(NapiStatus::Closing)));
+                Ok(())
+            },
+            move |_, result| {
+                settled.lock().unwrap().settle(result);
+                Ok(())
+            },
+        )?;
+        // NB: a pending future does not keep the event loop alive
+        tsfn.unref()?;
+
+        // NB: the first handler called takes the threadsafe function, sends the result and
+        // releases it. every failure is delivered as the result, so the future is always
+        // settled, and the threadsafe function is released even if `then` fails.
+        let tsfn = Rc::new(Cell::new(Some(tsfn)));
+        let deliver = {
+            let tsfn = tsfn.clone();
+            move |result: Result<T, PromiseError>| match tsfn.take() {
+                Some(tsfn) => {
+                    if let Err(status) = tsfn.non_blocking(result) {
+                        shared
+                            .lock()
+                            .unwrap()
+                            .settle(Err(PromiseError::Napi(status)));
+                    }
+                    tsfn.release()
+                }
+                None => Ok(()),
+            }
+        };
+        let fulfilled = deliver.clone();
+        let rejected = deliver.clone();
+        let result = self.then_value(
+            move |value| fulfilled(T::from_js(value).map_err(PromiseError::Napi)),
+            move |reason| {
+                rejected(Err(match rejection(reason) {
+                    Ok(message) => PromiseError::Rejected(message),
+                    Err(status) => PromiseError::Napi(status),
+                }))
+            },
+        );
+        if let Err(status) = result {
+            deliver(Err(PromiseError::Napi(status)))?;
+            return Err(status);
+        }
+
+        Ok(future)
+    }
+}
+
+#[cfg(feature = "v4")]
+/// The message of a rejection reason, the `message` of an Error or the reason as string.
+fn rejection(reason: JsValue) -> NapiResult<String> {
+    if reason.is_object()? {
+        let message = reason.as_object()?.get_named_property("message")?;
+        if message.is_string()? {
+            return message.as_string()?.get();
+        }
+    }
+    reason.coerce_to_string()?.get()
+}
+
+#[cfg(feature = "v4")]
+/// The error of awaiting a js promise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PromiseError {
+    /// the promise is rejected, with the message of the reason
+    Rejected(String),
+    /// the fulfilled value can not be converted, or the env is closing
+    Napi(NapiStatus),
+}
+
+#[cfg(feature = "v4")]
+impl std::fmt::Display for PromiseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PromiseError::Rejected(message) => write!(f, "{}", message),
+            PromiseError::Napi(status) => write!(f, "{}", status),
+        }
+    }
+}
+
+#[cfg(feature = "v4")]
+impl std::error::Error for PromiseError {}
+
+#[cfg(feature = "v4")]
+struct Shared<T> {
+    result: Option<Result<T, PromiseError>>,
+    waker: Option<Waker>,
+}
+
+#[cfg(feature = "v4")]
+impl<T> Shared<T> {
+    /// the first result wins
+    fn settle(&mut self, result: Result<T, PromiseError>) {
+        if self.result.is_none() {
+            self.result = Some(result);
+        }
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(feature = "v4")]
+/// A js promise as a rust future, created by JsPromise::into_future().
+pub struct JsFuture<T>(Arc<Mutex<Shared<T>>>);
+
+#[cfg(feature = "v4")]
+impl<T> Future for JsFuture<T> {
+    type Output = Result<T, PromiseError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.0.lock().unwrap();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<L: NapiValueT + Copy, R: NapiValueT + Copy> NapiValueT for JsPromise<L, R> {
+    fn from_raw(env: NapiEnv, raw: napi_value) -> JsPromise<L, R> {
+        // NB: a promise from js has no deferred object
+        JsPromise(
+            JsValue(env, raw),
+            std::ptr::null_mut(),
+            PhantomData,
+            PhantomData,
+        )
+    }
+
+    fn value(&self) -> JsValue {
+        self.0
+    }
+}
+
 impl<L: NapiValueT + Copy, R: NapiValueT + Copy> NapiValueCheck for JsPromise<L, R> {
     fn check(&self) -> NapiResult<bool> {
         Ok(napi_call!(=napi_is_promise, self.env(), self.raw()))
@@ -1,19 +1,209 @@
 use crate::{api, prelude::*};
-use std::{mem::MaybeUninit, os::raw::c_char};
+use std::{marker::PhantomData, mem::MaybeUninit};
 
+/// Element of a TypedArray, the rust type decides which kind of TypedArray it is.
+///
+/// | rust type       | TypedArray        |
+/// |-----------------|-------------------|
+/// | `i8`            | Int8Array         |
+/// | `u8`            | Uint8Array        |
+/// | `Uint8Clamped`  | Uint8ClampedArray |
+/// | `i16`           | Int16Array        |
+/// | `u16`           | Uint16Array       |
+/// | `i32`           | Int32Array        |
+/// | `u32`           | Uint32Array       |
+/// | `f32`           | Float32Array      |
+/// | `f64`           | Float64Array      |
+/// | `i64`           | BigInt64Array     |
+/// | `u64`           | BigUint64Array    |
+pub trait TypedArrayElement: private::Sealed + Copy + std::fmt::Debug + 'static {
+    const TYPE: NapiTypedarrayType;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Element of an Uint8ClampedArray, values written from js are clamped to 0..=255.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uint8Clamped(pub u8);
+
+impl From<u8> for Uint8Clamped {
+    fn from(value: u8) -> Uint8Clamped {
+        Uint8Clamped(value)
+    }
+}
+
+impl From<Uint8Clamped> for u8 {
+    fn from(value: Uint8Clamped) -> u8 {
+        value.0
+    }
+}
+
+macro_rules! typedarray_element {
+    ($($T:ty => $kind:ident),+ $(,)?) => {
+        $(
+            impl private::Sealed for $T {}
+            impl TypedArrayElement for $T {
+                const TYPE: NapiTypedarrayType = NapiTypedarrayType::$kind;
+            }
+        )+
+    };
+}
+
+typedarray_element!(
+    i8 => Int8Array,
+    u8 => Uint8Array,
+    Uint8Clamped => Uint8ClampedArray,
+    i16 => Int16Array,
+    u16 => Uint16Array,
+    i32 => Int32Array,
+    u32 => Uint32Array,
+    f32 => Float32Array,
+    f64 => Float64Array,
+    i64 => Bigint64Array,
+    u64 => Biguint64Array,
+);
+
+impl NapiTypedarrayType {
+    /// Size in bytes of one element of this kind of TypedArray.
+    pub fn element_size(&self) -> usize {
+        match self {
+            NapiTypedarrayType::Int8Array
+            | NapiTypedarrayType::Uint8Array
+            | NapiTypedarrayType::Uint8ClampedArray => 1,
+            NapiTypedarrayType::Int16Array | NapiTypedarrayType::Uint16Array => 2,
+            NapiTypedarrayType::Int32Array
+            | NapiTypedarrayType::Uint32Array
+            | NapiTypedarrayType::Float32Array => 4,
+            NapiTypedarrayType::Float64Array
+            | NapiTypedarrayType::Bigint64Array
+            | NapiTypedarrayType::Biguint64Array => 8,
+        }
+    }
+}
+
+/// Js TypedArray with element type `T`, e.g. `JsTypedArray<f32>` is a Float32Array.
 #[derive(Copy, Clone, Debug)]
-pub struct JsTypedArray(pub(crate) JsValue);
+pub struct JsTypedArray<T: TypedArrayElement>(pub(crate) JsValue, PhantomData<T>);
+
+impl<T: TypedArrayElement> JsTypedArray<T> {
+    pub(crate) fn from_value(value: JsValue) -> JsTypedArray<T> {
+        JsTypedArray(value, PhantomData)
+    }
+
+    /// Create a TypedArray of `len` elements over a new zero-filled ArrayBuffer.
+    pub fn new(env: NapiEnv, len: usize) -> NapiResult<JsTypedArray<T>> {
+        let size = len
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or(NapiStatus::InvalidArg)?;
+        let mut data = MaybeUninit::uninit();
+        let buffer = napi_call!(=napi_create_arraybuffer, env, size, data.as_mut_ptr());
+        JsArrayBuffer::from_raw(env, buffer).typedarray::<T>(0, len)
+    }
 
-impl JsTypedArray {
-    pub(crate) fn from_value(value: JsValue) -> JsTypedArray {
-        JsTypedArray(value)
+    /// Create a TypedArray with elements copied from `data`.
+    pub fn from_slice(env: NapiEnv, data: &[T]) -> NapiResult<JsTypedArray<T>> {
+        let mut array = JsTypedArray::<T>::new(env, data.len())?;
+        array.as_mut_slice()?.copy_from_slice(data);
+        Ok(array)
+    }
+
+    /// This API returns various properties of a typed array.
+    ///
+    /// Info includes (type, length, data, ArrayBuffer, byte_offset), the data pointer is
+    /// already adjusted by byte_offset so that it points to the first element.
+    #[allow(clippy::type_complexity)]
+    pub fn info(
+        &self,
+    ) -> NapiResult<(NapiTypedarrayType, usize, DataPointer, JsArrayBuffer, usize)> {
+        let mut typed = MaybeUninit::uninit();
+        let mut length = MaybeUninit::uninit();
+        let mut data = MaybeUninit::uninit();
+        let mut buffer = MaybeUninit::uninit();
+        let offset = napi_call!(
+            =napi_get_typedarray_info,
+            self.env(),
+            self.raw(),
+            typed.as_mut_ptr(),
+            length.as_mut_ptr(),
+            data.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+        );
+        unsafe {
+            Ok((
+                typed.assume_init(),
+                length.assume_init(),
+                data.assume_init(),
+                JsArrayBuffer::from_raw(self.env(), buffer.assume_init()),
+                offset,
+            ))
+        }
+    }
+
+    /// The kind of this TypedArray.
+    pub fn array_type(&self) -> NapiResult<NapiTypedarrayType> {
+        Ok(self.info()?.0)
+    }
+
+    /// The number of elements in this TypedArray.
+    pub fn len(&self) -> NapiResult<usize> {
+        Ok(self.info()?.1)
+    }
+
+    /// This TypedArray is empty.
+    pub fn is_empty(&self) -> NapiResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// The byte offset within the underlying ArrayBuffer of the first element.
+    pub fn byte_offset(&self) -> NapiResult<usize> {
+        Ok(self.info()?.4)
+    }
+
+    /// The ArrayBuffer underlying this TypedArray.
+    pub fn buffer(&self) -> NapiResult<JsArrayBuffer> {
+        Ok(self.info()?.3)
+    }
+
+    /// View the elements of this TypedArray, only the `len()` elements starting at
+    /// `byte_offset()` are visible.
+    ///
+    /// WARNING: the data is owned by the underlying ArrayBuffer, see JsArrayBuffer::buffer().
+    pub fn as_slice(&self) -> NapiResult<&[T]> {
+        let (_, len, data, _, _) = self.info()?;
+        if len == 0 {
+            return Ok(&[]);
+        }
+        unsafe { Ok(std::slice::from_raw_parts(data as *const T, len)) }
+    }
+
+    /// View the elements of this TypedArray (mut).
+    pub fn as_mut_slice(&mut self) -> NapiResult<&mut [T]> {
+        let (_, len, data, _, _) = self.info()?;
+        if len == 0 {
+            return Ok(&mut []);
+        }
+        unsafe { Ok(std::slice::from_raw_parts_mut(data as *mut T, len)) }
     }
 }
 
-napi_value_t!(JsTypedArray);
+impl<T: TypedArrayElement> NapiValueT for JsTypedArray<T> {
+    fn from_raw(env: NapiEnv, raw: napi_value) -> JsTypedArray<T> {
+        JsTypedArray(JsValue(env, raw), PhantomData)
+    }
+
+    fn value(&self) -> JsValue {
+        self.0
+    }
+}
 
-impl NapiValueCheck for JsTypedArray {
+impl<T: TypedArrayElement> NapiValueCheck for JsTypedArray<T> {
     fn check(&self) -> NapiResult<bool> {
-        Ok(napi_call!(=napi_is_typedarray, self.env(), self.raw()))
+        if !napi_call!(=napi_is_typedarray, self.env(), self.raw()) {
+            return Ok(false);
+        }
+        Ok(self.array_type()? == T::TYPE)
     }
 }
@@ -0,0 +1,191 @@
+use crate::{args::type_name, callback::throw_error as throw, prelude::*};
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// A native instance wrapped in a js object, whose borrows are tracked like a `RefCell`.
+///
+/// `NapiValueT::unwrap` hands out `&mut T` to every caller, so a method which calls into js,
+/// which calls another method of the same object, ends up with two `&mut T`. The guards of
+/// `borrow` and `borrow_mut` rule that out, a conflicting borrow throws a js Error instead.
+///
+/// `Wrapped<T>` is just a handle of the object, the instance is looked up on each borrow, so
+/// the instance is never accessed after it is taken by another handle.
+///
+/// ```ignore
+/// let counter = Wrapped::new(this, Counter::default())?;
+/// let count = counter.borrow()?.count;
+/// counter.borrow_mut()?.count += 1;
+/// ```
+///
+/// NB: the object is tagged with the type tag of a private cell type, so `unwrap` and
+/// `remove_wrap` of any type refuse it with `TypeTagError::Mismatch`, and the borrows can not be
+/// bypassed.
+#[derive(Debug)]
+pub struct Wrapped<T> {
+    object: JsObject,
+    _instance: PhantomData<RefCell<T>>,
+}
+
+/// The instance wrapped by `Wrapped<T>`, it is private so no other code can unwrap it.
+struct Cell<T>(RefCell<T>);
+
+/// The instance wrapped by `WrappedSync<T>`, it is private so no other code can unwrap it.
+struct Shared<T>(Arc<Mutex<T>>);
+
+impl<T> Clone for Wrapped<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Wrapped<T> {}
+
+impl<T> Wrapped<T> {
+    /// Wrap `instance` in `object`, it is dropped when the object is garbage-collected.
+    pub fn new(mut object: JsObject, instance: T) -> NapiResult<Wrapped<T>> {
+        object.wrap(Cell(RefCell::new(instance)), |_, _| Ok(()))?;
+        Ok(Wrapped::from_object(object))
+    }
+
+    /// The instance wrapped in `object` by `Wrapped::new`, None if there is no wrap.
+    pub fn of(object: JsObject) -> Result<Option<Wrapped<T>>, TypeTagError> {
+        Ok(crate::value::unwrap_raw::<Cell<T>>(object.value())?
+            .map(|_| Wrapped::from_object(object)))
+    }
+
+    fn from_object(object: JsObject) -> Wrapped<T> {
+        Wrapped {
+            object,
+            _instance: PhantomData,
+        }
+    }
+
+    /// The js object wrapping the instance.
+    pub fn object(&self) -> JsObject {
+        self.object
+    }
+
+    /// Borrow the instance, a js Error is thrown if it is mutably borrowed.
+    pub fn borrow(&self) -> NapiResult<Ref<'_, T>> {
+        self.cell()?.try_borrow().map_err(|_| {
+            throw(
+                self.object.env(),
+                format!("{} is already mutably borrowed", type_name::<T>()),
+            )
+        })
+    }
+
+    /// Mutably borrow the instance, a js Error is thrown if it is borrowed.
+    pub fn borrow_mut(&self) -> NapiResult<RefMut<'_, T>> {
+        self.cell()?.try_borrow_mut().map_err(|_| {
+            throw(
+                self.object.env(),
+                format!("{} is already borrowed", type_name::<T>()),
+            )
+        })
+    }
+
+    /// Remove the wrap and take the instance back, a js Error is thrown if it is borrowed.
+    pub fn take(self) -> NapiResult<T> {
+        if self.cell()?.try_borrow_mut().is_err() {
+            return Err(throw(
+                self.object.env(),
+                format!("{} is already borrowed", type_name::<T>()),
+            ));
+        }
+        let mut object = self.object;
+        Ok(object.remove_wrap::<Cell<T>>()?.0.into_inner())
+    }
+
+    fn cell(&self) -> NapiResult<&RefCell<T>> {
+        // NB: only shared references of the cell are created, the instance lives as long as
+        // the wrap, which is not removed while the cell is borrowed
+        match crate::value::unwrap_raw::<Cell<T>>(self.object.value())? {
+            Some(cell) => Ok(unsafe { &(*cell).0 }),
+            None => Err(throw(
+                self.object.env(),
+                format!("{} is already taken", type_name::<T>()),
+            )),
+        }
+    }
+}
+
+/// A native instance wrapped in a js object, which is shared by `Arc<Mutex<T>>` with other
+/// threads, e.g. an async work or a future spawned on the runtime.
+///
+/// ```ignore
+/// let state = WrappedSync::new(this, Progress::default())?.state()?;
+/// std::thread::spawn(move || state.lock().unwrap().done += 1);
+/// ```
+///
+/// NB: the object is tagged with the type tag of a private type, so `unwrap` and `remove_wrap`
+/// of any type refuse it with `TypeTagError::Mismatch`.
+#[derive(Debug)]
+pub struct WrappedSync<T> {
+    object: JsObject,
+    _instance: PhantomData<Arc<Mutex<T>>>,
+}
+
+impl<T> Clone for WrappedSync<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WrappedSync<T> {}
+
+impl<T: Send> WrappedSync<T> {
+    /// Wrap `instance` in `object`, it is dropped when the object is garbage-collected and the
+    /// other threads release it.
+    pub fn new(mut object: JsObject, instance: T) -> NapiResult<WrappedSync<T>> {
+        object.wrap(Shared(Arc::new(Mutex::new(instance))), |_, _| Ok(()))?;
+        Ok(WrappedSync::from_object(object))
+    }
+
+    /// The instance wrapped in `object` by `WrappedSync::new`, None if there is no wrap.
+    pub fn of(object: JsObject) -> Result<Option<WrappedSync<T>>, TypeTagError> {
+        Ok(crate::value::unwrap_raw::<Shared<T>>(object.value())?
+            .map(|_| WrappedSync::from_object(object)))
+    }
+
+    fn from_object(object: JsObject) -> WrappedSync<T> {
+        WrappedSync {
+            object,
+            _instance: PhantomData,
+        }
+    }
+
+    /// The js object wrapping the instance.
+    pub fn object(&self) -> JsObject {
+        self.object
+    }
+
+    /// The shared instance, which can be moved to other threads.
+    pub fn state(&self) -> NapiResult<Arc<Mutex<T>>> {
+        Ok(self.shared()?.clone())
+    }
+
+    /// Lock the instance, it blocks while another thread holds the lock. A js Error is thrown
+    /// if a thread panicked while holding the lock.
+    pub fn lock(&self) -> NapiResult<MutexGuard<'_, T>> {
+        self.shared()?.lock().map_err(|_| {
+            throw(
+                self.object.env(),
+                format!("{} is poisoned", type_name::<T>()),
+            )
+        })
+    }
+
+    fn shared(&self) -> NapiResult<&Arc<Mutex<T>>> {
+        match crate::value::unwrap_raw::<Shared<T>>(self.object.value())? {
+            Some(shared) => Ok(unsafe { &(*shared).0 }),
+            None => Err(throw(
+                self.object.env(),
+                format!("{} is already taken", type_name::<T>()),
+            )),
+        }
+    }
+}
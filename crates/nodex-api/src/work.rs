@@ -50,7 +50,10 @@ impl<T> NapiAsyncWork<T> {
                     Box<dyn FnMut(NapiEnv, NapiStatus, T) -> NapiResult<()>>,
                     T,
                 ) = std::mem::transmute(&mut *(data as *mut _));
-                execute(state);
+                crate::callback::guard("NapiAsyncWork::execute", || {
+                    execute(state);
+                    Ok(())
+                });
             }
         }
         extern "C" fn napi_async_complete_callback<T>(
@@ -59,13 +62,13 @@ impl<T> NapiAsyncWork<T> {
             data: DataPointer,
         ) {
             unsafe {
-                let mut pair: Box<(
+                let pair: Box<(
                     Box<dyn FnMut(&mut T)>,
-                    Box<dyn FnMut(NapiEnv, NapiStatus, T)>,
+                    Box<dyn FnMut(NapiEnv, NapiStatus, T) -> NapiResult<()>>,
                     T,
                 )> = Box::from_raw(data as _);
-                let mut complete = pair.1;
-                complete(env, status, pair.2);
+                let (_, mut complete, state) = *pair;
+                crate::callback::guard("NapiAsyncWork::complete", || complete(env, status, state));
             }
         }
 
@@ -1,2 +1,3 @@
 #[doc = include_str!("../README.md")]
 pub use nodex_api::*;
+pub use nodex_macros::{class, function, methods};
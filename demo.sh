@@ -1,5 +1,6 @@
 #!/bin/bash
 bash examples/demo.sh || exit
+bash examples/class.sh || exit
 bash examples/descriptor.sh || exit
 bash examples/element.sh || exit
 bash examples/func.sh || exit
@@ -7,3 +8,4 @@ bash examples/plugin.sh || exit
 bash examples/promise.sh || exit
 bash examples/script.sh || exit
 bash examples/tsfn.sh || exit
+bash examples/worker.sh || exit
@@ -0,0 +1,49 @@
+const { Counter } = require("./class.node")
+
+let counter = new Counter(10)
+counter.step = 2
+console.log(counter.increase(), counter.count)
+
+let forked = counter.fork()
+forked.increase()
+console.log(forked instanceof Counter, forked.count, counter.count)
+
+let zero = Counter.zero()
+console.log(zero.count, zero.step)
+
+try {
+  counter.increase.call({})
+} catch (e) {
+  console.log(`${e}`)
+}
+
+try {
+  counter.step = "3"
+} catch (e) {
+  console.log(`${e}`)
+}
+
+// a reentrant call can not alias the mutably borrowed counter
+counter.peek(() => console.log(`peek: ${counter.count}`))
+try {
+  counter.update(() => counter.increase())
+} catch (e) {
+  console.log(`${e}`)
+}
+
+const { Point } = require("./class.node")
+const point = new Point(1, 2)
+console.log(point.id, point.x, point.y, new Point(3).y)
+
+for (const call of [
+  () => Point(1, 2),
+  () => new Point(),
+  () => new Point("1"),
+  () => counter.increase.call(point),
+]) {
+  try {
+    call()
+  } catch (e) {
+    console.log(`${e}`)
+  }
+}
@@ -0,0 +1 @@
+demo.sh
\ No newline at end of file
@@ -0,0 +1,91 @@
+use nodex::prelude::*;
+use std::cell::Cell;
+nodex::napi_module!(init);
+
+fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
+    // a class defined by a closure, the data counts the created points
+    let point = env.class_with_context(
+        "Point",
+        Cell::new(0u32),
+        |ctx| {
+            if ctx.new_target()?.is_none() {
+                ctx.env()
+                    .throw_type_error("Class constructor Point cannot be invoked without 'new'")?;
+                return Err(NapiStatus::PendingException);
+            }
+            let created = ctx.data();
+            created.set(created.get() + 1);
+
+            let env = ctx.env();
+            let mut this = ctx.this::<JsObject>()?;
+            this.set("id", env.uint32(created.get())?)?;
+            this.set("x", env.double(ctx.arg::<f64>(0)?)?)?;
+            this.set("y", env.double(ctx.arg::<Option<f64>>(1)?.unwrap_or(0.))?)?;
+            this.wrap(created.get(), |_, _| Ok(()))?;
+            Ok(this)
+        },
+        [],
+    )?;
+    exports.set("Point", point)?;
+    Ok(())
+}
+
+#[nodex::class]
+struct Counter {
+    count: i64,
+    step: i64,
+}
+
+#[nodex::methods]
+impl Counter {
+    #[constructor]
+    fn new(count: i64) -> Self {
+        Counter { count, step: 1 }
+    }
+
+    fn increase(&mut self) -> i64 {
+        self.count += self.step;
+        self.count
+    }
+
+    /// a new counter starts from the current count
+    fn fork(&self) -> Self {
+        Counter {
+            count: self.count,
+            step: self.step,
+        }
+    }
+
+    /// the counter is borrowed while `callback` runs, it can read the counter
+    fn peek(&self, env: NapiEnv, callback: JsFunction) -> NapiResult<()> {
+        callback.call(env.object()?, ())?;
+        Ok(())
+    }
+
+    /// the counter is mutably borrowed while `callback` runs, accessing it throws
+    fn update(&mut self, env: NapiEnv, callback: JsFunction) -> NapiResult<()> {
+        callback.call(env.object()?, ())?;
+        self.count += self.step;
+        Ok(())
+    }
+
+    #[method(name = "zero")]
+    fn create_zero() -> Self {
+        Counter::new(0)
+    }
+
+    #[getter]
+    fn count(&self) -> i64 {
+        self.count
+    }
+
+    #[getter]
+    fn step(&self) -> i64 {
+        self.step
+    }
+
+    #[setter]
+    fn set_step(&mut self, step: i64) {
+        self.step = step;
+    }
+}
@@ -25,6 +25,7 @@ demo.thread((result) => {
 })
 
 console.log('array_index[0]:', demo.buffer_index(Buffer.from([1,2,3,4,5])))
+console.log('calls:', demo.calls(), demo.calls())
 console.log(demo.buffer)
 
 demo.delay(() => {
@@ -19,8 +19,18 @@ fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
     })?;
 
     println!("unwrap: {:?}", obj.unwrap::<[usize; 2]>());
+    // the object is tagged with the type of the wrapped instance
+    println!("unwrap as String: {:?}", obj.unwrap::<String>());
+    println!("remove wrap as String: {:?}", obj.remove_wrap::<String>());
     println!("remove wrap: {:?}", obj.remove_wrap::<[usize; 2]>());
 
+    let external = env.external(42u32, |_, _| Ok(()))?;
+    println!("external: {:?}", external.get());
+    println!(
+        "external as String: {:?}",
+        external.value().as_external::<String>()?.get()
+    );
+
     let label = "func";
     let name = env.string(label)?;
     let symbol = env.symbol()?;
@@ -209,10 +219,34 @@ fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
     let value = env.get_instance_data::<usize>()?;
     println!("get instance data: {:?}", value);
 
+    // the replaced data is passed to its finalizer
+    env.set_instance_data(300usize, |_, value| {
+        println!("drop instance data: {}", value);
+        Ok(())
+    })?;
+
+    // each type has its own state, the instance data above is kept
+    struct Calls(std::cell::Cell<u32>);
+    env.set_state_with_finalizer(String::from("demo"), |_, name| {
+        println!("drop state: {}", name);
+        Ok(())
+    })?;
+    println!("get state: {:?}", env.state::<String>()?);
+    println!("get instance data: {:?}", env.get_instance_data::<usize>()?);
+
+    exports.set_named_property(
+        "calls",
+        env.func(|this, ()| {
+            let calls = this.env().state_or_init(|| Ok(Calls(Default::default())))?;
+            calls.0.set(calls.0.get() + 1);
+            Ok(calls.0.get())
+        })?,
+    )?;
+
     exports.set_named_property(
         "buffer_index",
         env.func(|this, a1: JsValue| {
-            let a1 = a1.as_buffer::<5>()?;
+            let a1 = a1.as_buffer()?;
             this.env().double(a1[0] as f64)
         })?,
     )?;
@@ -220,7 +254,7 @@ fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
     let external = env.external("ext data".into(), |_, _: String| Ok(()))?;
     assert_eq!("ext data", external.get()?);
 
-    let ext_buffer: JsBuffer<10> = env.buffer_copy([10; 10])?;
+    let ext_buffer: JsBuffer = env.buffer_copy([10; 10])?;
     exports.set_named_property("buffer", ext_buffer)?;
 
     exports.set(
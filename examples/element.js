@@ -2,3 +2,50 @@ const element = require("./element.node")
 
 console.log(element)
 
+console.log(element.double(new Float32Array([1, 2.5, -4])))
+
+// only the viewed elements are doubled
+const floats = new Float32Array([1, 2, 3, 4])
+element.double(floats.subarray(1, 3))
+console.log(floats)
+
+console.log(element.reverse(Buffer.from("hello, world")).toString())
+console.log(element.reverse(Buffer.alloc(0)))
+
+try {
+    element.double(new Float64Array([1, 2]))
+} catch (e) {
+    console.log(e.message)
+}
+
+console.log(element.bigint_i128 === -(2n ** 127n))
+console.log(element.add128(2n ** 64n, 2n ** 100n))
+console.log(element.negate(2n ** 200n + 1n))
+
+for (const args of [[2n ** 127n, 2n ** 127n], [-1n, 1n], [1, 2]]) {
+    try {
+        element.add128(...args)
+    } catch (e) {
+        console.log(e.message)
+    }
+}
+
+console.log(element.slice_utf16("a😀b", 1, 3), element.slice_utf16("a😀b", 3, 10))
+console.log(element.shout("héllo"), element.shout("a longer string here"))
+
+const cyclic = { name: "cyclic", list: [1, "two", 3n, null, undefined], nested: { deep: { deeper: {} } } }
+cyclic.self = cyclic
+console.log(element.inspect(cyclic))
+console.log(element.inspect([Symbol("tag"), () => 1, function named() {}, new Error("bad"), new Uint8Array(4)]))
+console.log(element.inspect(new (class Point { constructor() { this.x = 1 } })()))
+console.log(element.inspect(Object.create(null)))
+console.log(element.debug("nested", { a: { b: { c: 1 } } }))
+console.log(element.inspect({ get boom() { throw new Error("boom") } }))
+
+const hidden = Object.create(null)
+Object.defineProperty(hidden, "hidden", { value: 1, enumerable: false })
+hidden[Symbol("sym")] = 2
+hidden.shown = 3
+console.log(element.own_keys(hidden), element.own_keys({}))
+
+console.log(element.squares(5))
@@ -1,18 +1,25 @@
 use nodex::prelude::*;
+use std::borrow::Cow;
 
 nodex::napi_module!(init);
 
 fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
     exports.set("string", env.string("napi string")?)?;
+    exports.set("string_latin1", env.string_latin1(b"caf\xe9")?)?;
     exports.set("symbol", env.symbol()?)?;
     exports.set("array", env.array()?)?;
     exports.set("arraybuffer", env.arraybuffer([1, 2, 3, 4, 5])?)?;
     exports.set("bigint_int64", env.bigint_i64(i64::MAX)?)?;
     exports.set("bigint_unt64", env.bigint_u64(u64::MAX)?)?;
+    exports.set("bigint_i128", env.bigint_words(true, &[0, 1 << 63])?)?;
     exports.set("boolean_true", env.boolean(true)?)?;
     exports.set("boolean_false", env.boolean(false)?)?;
-    exports.set("buffer_10", env.buffer::<10>()?)?;
+    exports.set("buffer_10", env.buffer(10)?)?;
     exports.set("buffer_copy_10", env.buffer_copy([0; 10])?)?;
+    exports.set(
+        "buffer_external",
+        JsBuffer::from_external(env, b"external".to_vec())?,
+    )?;
 
     let arraybuffer = env.arraybuffer([0; 10])?;
     exports.set_named_property("arraybuffer_10", arraybuffer)?;
@@ -38,9 +45,106 @@ fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
         env.promise(|_| {}, |_: JsPromise<JsValue, JsValue>, _, _: ()| Ok(()))?
             .value(),
     )?;
+    exports.set_named_property("typedarray", arraybuffer.typedarray::<u8>(2, 5)?)?;
+    exports.set_named_property(
+        "float32array",
+        JsTypedArray::<f32>::from_slice(env, &[1.5, 2.5, 3.5])?,
+    )?;
+    exports.set_named_property("bigint64array", env.typedarray::<i64>(3)?)?;
+    exports.set_named_property(
+        "clamped",
+        JsTypedArray::from_slice(env, &[Uint8Clamped(0), Uint8Clamped(255)])?,
+    )?;
+    exports.set_named_property(
+        "reverse",
+        env.func(move |this, buffer: JsBuffer| {
+            let mut bytes = buffer.as_slice()?.to_vec();
+            bytes.reverse();
+            JsBuffer::from_vec(this.env(), bytes)
+        })?,
+    )?;
+    exports.set_named_property(
+        "double",
+        env.func(move |_, mut array: JsTypedArray<f32>| {
+            array.as_mut_slice()?.iter_mut().for_each(|x| *x *= 2.);
+            Ok(array)
+        })?,
+    )?;
+    exports.set_named_property(
+        "add128",
+        env.func(move |_, (a, b): (u128, u128)| Ok(a.checked_add(b).ok_or("u128 overflow")))?,
+    )?;
+    exports.set_named_property(
+        "negate",
+        env.func(move |this, bigint: JsBigInt| {
+            let (negative, words) = bigint.to_words()?;
+            this.env().bigint_words(!negative, &words)
+        })?,
+    )?;
+    exports.set_named_property(
+        "slice_utf16",
+        env.func(
+            move |this, (string, start, end): (JsString, usize, usize)| {
+                let units = string.get_utf16()?;
+                let end = end.min(units.len());
+                this.env().string_utf16(&units[start.min(end)..end])
+            },
+        )?,
+    )?;
+    exports.set_named_property(
+        "shout",
+        env.func(move |_, string: JsString| {
+            // NB: short strings are not allocated
+            let mut buffer = [0u8; 16];
+            let string = string.get_str(&mut buffer)?;
+            Ok(format!(
+                "{}: {}!",
+                matches!(string, Cow::Borrowed(_)),
+                string.to_uppercase()
+            ))
+        })?,
+    )?;
+    exports.set_named_property(
+        "inspect",
+        env.func(move |_, value: JsValue| Ok(value.pretty().to_string()))?,
+    )?;
+    exports.set_named_property(
+        "debug",
+        env.func(move |_, (name, value): (String, JsValue)| {
+            #[derive(Debug)]
+            #[allow(dead_code)]
+            struct Entry {
+                name: String,
+                value: Pretty,
+            }
+            let value = value.pretty().depth(1);
+            Ok(format!("{:?}", Entry { name, value }))
+        })?,
+    )?;
+    exports.set_named_property(
+        "own_keys",
+        env.func(move |_, object: JsObject| {
+            let mut keys = vec![];
+            for key in object.own_keys()? {
+                keys.push(key?.pretty().to_string());
+            }
+            Ok((keys, object.prototype()?.is_some()))
+        })?,
+    )?;
     exports.set_named_property(
-        "typedarray",
-        arraybuffer.typedarray(NapiTypedarrayType::Uint8Array, 0, 5)?,
+        "squares",
+        env.func(move |this, n: u32| {
+            this.env().local_scope_escape(|scope| {
+                scope.escape(|inner| {
+                    let mut squares = inner.array()?;
+                    for i in 0..n {
+                        // NB: the temporary numbers are released with the inner scope
+                        squares.set(i, inner.uint32(i * i)?)?;
+                    }
+                    Ok(squares)
+                })
+            })
+        })?,
     )?;
 
     Ok(())
@@ -3,3 +3,63 @@ const func = require("./func.node")
 let ret = func.func("hello", 100)
 
 console.log(ret)
+
+console.log(func.add(1, 2))
+console.log(func.greet("nodex", 2))
+
+console.log(func.scale([1, 2, 3]), func.scale([1, 2, 3], 10))
+console.log(func.describe("nodex", { fast: true, slow: false, safe: true }))
+console.log(func.join(", ", "a", "b", "c"), func.join("-"), func.count(1, 2, 3))
+console.log(func.kinds(1, "a", null, {}, () => {}))
+
+try {
+  func.add(1, "2")
+} catch (e) {
+  console.error(`${e.name}: ${e.message}`)
+}
+
+for (const call of [
+  () => func.scale("1, 2, 3"),
+  () => func.describe("nodex", 1),
+  () => func.func(1, 2),
+  () => func.divide(1, 0),
+  () => func.scale(),
+  () => func.join(),
+  () => func.join("-", "a", 1),
+  () => func.pow2(60),
+]) {
+  try {
+    call()
+  } catch (e) {
+    console.error(`${e.name}: ${e.message}`)
+  }
+}
+
+console.log(func.divide(6, 3), func.pow2(52))
+
+func.remember(() => "remembered")
+console.log(func.recall())
+func.forget()
+console.log(func.recall())
+
+require("v8").setFlagsFromString("--expose-gc")
+const gc = require("vm").runInNewContext("gc")
+
+func.watch({ watched: true })
+console.log(func.alive())
+setTimeout(() => {
+  gc()
+  console.log(func.alive())
+}, 0)
+
+// finalizers run after the gc, in a later tick
+const closures = func.closures()
+func.churn(100)
+console.log(`closures created: ${func.closures() - closures}`)
+;(async () => {
+  for (let i = 0; i < 3; i++) {
+    gc()
+    await new Promise((resolve) => setImmediate(resolve))
+  }
+  console.log(`closures leaked: ${func.closures() - closures}`)
+})()
@@ -1,6 +1,35 @@
 use nodex::prelude::*;
+use std::{collections::HashMap, sync::Mutex};
 nodex::napi_module!(init);
 
+/// exported as `add` by nodex::napi_module!
+#[nodex::function]
+fn add(a: f64, b: f64) -> f64 {
+    a + b
+}
+
+/// exported as `greet` by nodex::napi_module!
+#[nodex::function(name = "greet")]
+fn hello(env: NapiEnv, name: String, times: u32) -> NapiResult<JsString> {
+    env.string(format!("hello, {}", name).repeat(times as usize))
+}
+
+/// a trailing `Rest<T>` takes the remaining arguments
+#[nodex::function]
+fn join(separator: String, words: Rest<String>) -> String {
+    words.0.join(&separator)
+}
+
+/// 2 to the power of `exp`, which is refused beyond `Number.MAX_SAFE_INTEGER`
+#[nodex::function]
+fn pow2(exp: u32) -> u64 {
+    1u64 << exp.min(63)
+}
+
+/// references kept by rust state, they may be moved to other threads
+static CALLBACK: Mutex<Option<Persistent<JsFunction>>> = Mutex::new(None);
+static WATCHED: Mutex<Option<Weak<JsObject>>> = Mutex::new(None);
+
 fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
     exports.set_named_property(
         "func",
@@ -14,5 +43,123 @@ fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
         )?,
     )?;
 
+    // plain rust values are converted from and to js values
+    exports.set(
+        "scale",
+        env.func(|_, (values, factor): (Vec<f64>, Option<f64>)| {
+            let factor = factor.unwrap_or(2.);
+            Ok(values.into_iter().map(|v| v * factor).collect::<Vec<_>>())
+        })?,
+    )?;
+
+    // all the arguments passed by js
+    exports.set(
+        "count",
+        env.func(|_, args: JsArgs| Ok(args.0.len() as u32))?,
+    )?;
+
+    // the args are inspected one by one
+    exports.set(
+        "kinds",
+        env.func_with_context((), |ctx| {
+            ctx.args_iter()
+                .map(|arg| Ok(format!("{:?}", arg.kind()?)))
+                .collect::<NapiResult<Vec<_>>>()
+        })?,
+    )?;
+
+    exports.set(
+        "describe",
+        env.func(|_, (name, tags): (String, HashMap<String, bool>)| {
+            let mut tags = tags
+                .into_iter()
+                .filter_map(|(tag, on)| on.then_some(tag))
+                .collect::<Vec<_>>();
+            tags.sort();
+            Ok((name, tags))
+        })?,
+    )?;
+
+    // a panic is thrown as an Error instead of aborting node
+    exports.set(
+        "divide",
+        env.func(|_, (a, b): (i32, i32)| {
+            if b == 0 {
+                panic!("divide by zero");
+            }
+            Ok(a / b)
+        })?,
+    )?;
+
+    exports.set(
+        "remember",
+        env.func(|_, callback: JsFunction| {
+            *CALLBACK.lock().unwrap() = Some(Persistent::new(callback)?);
+            Ok(())
+        })?,
+    )?;
+
+    exports.set(
+        "recall",
+        env.func(|this, ()| match CALLBACK.lock().unwrap().as_ref() {
+            Some(callback) => Ok(Some(callback.get()?.call(this, ())?)),
+            None => Ok(None),
+        })?,
+    )?;
+
+    // the reference is deleted by the js thread later
+    exports.set(
+        "forget",
+        env.func(|_, ()| {
+            let callback = CALLBACK.lock().unwrap().take();
+            std::thread::spawn(move || drop(callback)).join().unwrap();
+            Ok(())
+        })?,
+    )?;
+
+    exports.set(
+        "watch",
+        env.func(|_, object: JsObject| {
+            *WATCHED.lock().unwrap() = Some(Weak::new(object)?);
+            Ok(())
+        })?,
+    )?;
+
+    exports.set(
+        "alive",
+        env.func(|_, ()| match WATCHED.lock().unwrap().as_ref() {
+            Some(watched) => Ok(watched.get()?.is_some()),
+            None => Ok(false),
+        })?,
+    )?;
+
+    // the closures created by churn are freed once their js values are garbage-collected
+    exports.set(
+        "closures",
+        env.func(|_, ()| Ok(nodex::callback::live_closures() as u32))?,
+    )?;
+
+    exports.set(
+        "churn",
+        env.func(|this, times: u32| {
+            let env = this.env();
+            for i in 0..times {
+                let object = env.object()?;
+                object.define_properties([
+                    DescriptorMethodBuilder::new()
+                        .with_utf8name("method")
+                        .with_method(move |this, ()| this.env().uint32(i))
+                        .build()?,
+                    DescriptorAccessorBuilder::<JsNumber, JsNumber>::new()
+                        .with_utf8name("accessor")
+                        .with_getter(move |this| this.env().uint32(i))
+                        .build()?,
+                ])?;
+                env.func(move |this, ()| this.env().uint32(i))?;
+            }
+            Ok(())
+        })?,
+    )?;
+
     Ok(())
 }
@@ -1,4 +1,9 @@
+const assert = require("assert")
 const plugin = require("./plugin.node")
 
-plugin.hello_world()
+assert.deepStrictEqual(Object.keys(plugin), ["helloworld"])
+plugin.helloworld.hello_world()
 
+// the namespace of a plugin is read-only
+plugin.helloworld = null
+assert.strictEqual(typeof plugin.helloworld, "object")
@@ -1,8 +1,10 @@
 use nodex::prelude::*;
-nodex::napi_module!(init);
 
-fn init(env: NapiEnv, exports: JsObject) -> NapiResult<()> {
-    nodex_plugin_helloworld::init(env, exports)?;
+// NB: the plugin is installed on `exports.helloworld` once the crate is linked
+nodex::link_plugin!(nodex_plugin_helloworld);
+
+nodex::napi_module!(init);
 
+fn init(_env: NapiEnv, _exports: JsObject) -> NapiResult<()> {
     Ok(())
 }
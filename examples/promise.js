@@ -15,3 +15,12 @@ p2.then(resolved => {
 })
 
 console.log("test napi promise")
+
+promise.sleep(100).then(value => console.log("sleep:", value))
+promise.fail("failed").catch(e => console.log("fail:", e.message))
+promise.boom().catch(e => console.log("boom:", e.message))
+
+promise.hook_len(async () => "hello").then(len => console.log("hook_len:", len))
+promise.hook_len(async () => { throw new Error("hook failed") })
+  .catch(e => console.log("hook_len:", e.message))
+promise.hook_len(async () => 1).catch(e => console.log("hook_len:", e.message))
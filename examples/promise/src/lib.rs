@@ -1,6 +1,33 @@
 use nodex::prelude::*;
 nodex::napi_module!(init);
 
+/// resolved after `ms` milliseconds
+#[nodex::function]
+async fn sleep(ms: u32) -> String {
+    tokio::time::sleep(std::time::Duration::from_millis(ms as u64)).await;
+    format!("slept {} ms", ms)
+}
+
+/// rejected with an Error of `message`
+#[nodex::function]
+async fn fail(message: String) -> Result<u32, String> {
+    Err(message)
+}
+
+/// rejected since the future panics
+#[nodex::function]
+async fn boom() -> u32 {
+    panic!("boom")
+}
+
+/// awaits the promise returned by the js `hook`, resolved with the length of its string
+#[nodex::function]
+fn hook_len(env: NapiEnv, hook: JsFunction) -> NapiResult<JsPromise<JsNumber, JsValue>> {
+    let promise: JsPromise<JsValue, JsValue> = hook.call(env.object()?, ())?.as_promise()?;
+    let future = promise.into_future::<String>()?;
+    env.spawn_future(async move { future.await.map(|value| value.len() as u32) })
+}
+
 fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
     exports.set_named_property(
         "create",
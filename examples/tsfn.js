@@ -3,3 +3,27 @@ const tsfn = require('./tsfn.node')
 tsfn.create(function (value) {
   console.log(this, value)
 })
+
+// listeners are kept by the emitter
+const plain = {}
+tsfn.progress(plain, 3)
+plain.on("progress", (step, steps) => console.log(`plain: ${step}/${steps}`))
+  .once("done", (message) => console.log(`plain: ${message}`))
+
+// events are emitted by EventEmitter.prototype.emit
+const { EventEmitter } = require("events")
+class Task extends EventEmitter {}
+const task = new Task()
+task.on("progress", (step, steps) => console.log(`task: ${step}/${steps}`))
+task.on("done", (message) => console.log(`task: ${message}`))
+tsfn.progress(task, 2)
+
+// the returned value, or the thrown exception, is delivered to rust
+tsfn.ask((a, b) => a + b).then((sum) => console.log(`ask: ${sum}`))
+tsfn.ask(() => { throw new Error("no answer") }).catch((e) => console.log(`ask: ${e.message}`))
+
+let ticks = 0
+tsfn.count(() => { if (++ticks == 4) console.log(`count: ${ticks}`) }, 4)
+
+const tally = tsfn.tally(5)
+tally.done.then(() => console.log(`tally: ${tally.value()}`))
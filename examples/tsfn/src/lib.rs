@@ -1,5 +1,5 @@
 /// This addon shows how to use `nodex::NapiThreadsafeFunction`.
-use nodex::prelude::*;
+use nodex::{events::Emitter, prelude::*};
 nodex::napi_module!(init);
 
 fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
@@ -41,5 +41,70 @@ fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
         })?,
     )?;
 
+    // emit events to js listeners from another thread, at most 2 events are queued
+    exports.set_named_property(
+        "progress",
+        env.func(|_, (target, steps): (JsObject, u32)| {
+            let emitter = Emitter::with_queue_size(target, 2)?;
+            std::thread::spawn(move || {
+                for step in 1..=steps {
+                    emitter.emit("progress", (step, steps)).unwrap();
+                }
+                emitter.emit("done", "finished").unwrap();
+            });
+            Ok(())
+        })?,
+    )?;
+
+    // call a js function from the runtime and get its returned value
+    exports.set_named_property(
+        "ask",
+        env.func(|this, callback: JsFunction| {
+            let add = ThreadsafeFunction::<(u32, u32), u32>::new(callback, 1)?;
+            this.env().spawn_future(async move {
+                let sum = add.call_with_return((1, 2)).await?;
+                add.call_with_return((sum, 3)).await
+            })
+        })?,
+    )?;
+
+    // the wrapped state is shared with the runtime, and read by `tally.value()`
+    exports.set_named_property(
+        "tally",
+        env.func(|this, n: u32| {
+            let env = this.env();
+            let mut tally = env.object()?;
+            let shared = WrappedSync::new(tally, 0u32)?.state()?;
+            tally.set_named_property(
+                "value",
+                env.func(|this, ()| {
+                    let tally = WrappedSync::<u32>::of(this)?.ok_or(NapiStatus::InvalidArg)?;
+                    let value = *tally.lock()?;
+                    Ok(value)
+                })?,
+            )?;
+            let done = env.spawn_future(async move {
+                for _ in 0..n {
+                    *shared.lock().unwrap() += 1;
+                }
+            })?;
+            tally.set_named_property("done", done)?;
+            Ok(tally)
+        })?,
+    )?;
+
+    // each thread holds a handle, the function is finalized after all threads are done
+    exports.set_named_property(
+        "count",
+        env.func(|_, (callback, threads): (JsFunction, u32)| {
+            let tick = ThreadsafeFunction::<(u32,), ()>::new(callback, 0)?;
+            for id in 0..threads {
+                let tick = tick.clone();
+                std::thread::spawn(move || tick.call((id,), NapiTsfnMode::Blocking).unwrap());
+            }
+            Ok(())
+        })?,
+    )?;
+
     Ok(())
 }
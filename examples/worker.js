@@ -0,0 +1,53 @@
+const assert = require("assert")
+const { Worker, isMainThread, parentPort, workerData } = require("worker_threads")
+const worker = require("./worker.node")
+
+if (isMainThread) {
+  worker.leak({ from: "main" })
+  console.log("main:", worker.id, "calls:", worker.calls(), worker.calls())
+
+  const workers = [1, 2, 3].map(() => new Promise((resolve, reject) => {
+    let result
+    new Worker(__filename)
+      .on("message", message => result = message)
+      .on("error", reject)
+      .on("exit", () => resolve(result))
+  }))
+
+  Promise.all(workers).then(results => {
+    // each worker initializes the addon in its own env, with its own states
+    assert.deepStrictEqual(results.map(result => result.id).sort(), [1, 2, 3])
+    for (const result of results) {
+      assert.strictEqual(result.calls, 1)
+      assert.match(result.leaked, /js values can not be shared between envs/)
+    }
+    assert.strictEqual(worker.calls(), 3)
+    assert.strictEqual(worker.loaded(), 1)
+    // the references of the workers are gone with their envs
+    assert.strictEqual(worker.release(), 3)
+    console.log("workers:", results.length, "loaded:", worker.loaded())
+  }).then(() => new Promise((resolve, reject) => {
+    // the worker is terminated while its future is pending, which completes after the env is
+    // torn down
+    const pending = new Worker(__filename, { workerData: "pending" })
+      .on("message", () => pending.terminate())
+      .on("error", reject)
+      .on("exit", resolve)
+  })).then(() => worker.sleep(500)).then(ms => {
+    assert.strictEqual(ms, 500)
+    assert.strictEqual(worker.loaded(), 1)
+    console.log("pending future: ok")
+  })
+} else if (workerData === "pending") {
+  worker.sleep(200)
+  parentPort.postMessage("pending")
+} else {
+  worker.keep({ from: "worker" })
+  let leaked = "not detected"
+  try {
+    worker.leaked()
+  } catch (e) {
+    leaked = e.message
+  }
+  parentPort.postMessage({ id: worker.id, calls: worker.calls(), leaked })
+}
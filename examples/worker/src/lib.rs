@@ -0,0 +1,93 @@
+use nodex::{module::ModuleContext, prelude::*};
+use std::{cell::Cell, sync::Mutex};
+
+nodex::napi_module!(init);
+
+/// A js value kept in a global, which is a bug: the value only lives in the env and the handle
+/// scope it is created in. Debug builds detect it when another env uses the value.
+#[derive(Copy, Clone)]
+struct Leaked(JsValue);
+unsafe impl Send for Leaked {}
+
+static LEAKED: Mutex<Option<Leaked>> = Mutex::new(None);
+
+/// References kept across envs, which may be dropped after their env is torn down.
+static KEPT: Mutex<Vec<Persistent<JsObject>>> = Mutex::new(Vec::new());
+
+/// The calls of `calls()` in each env.
+struct Calls(Cell<u32>);
+
+fn init(context: &ModuleContext, mut exports: JsObject) -> NapiResult<()> {
+    let env = context.env();
+    let id = context.id();
+
+    context.on_unload(move |_| {
+        println!("unload env {}", id);
+        Ok(())
+    })?;
+
+    exports.set_named_property("id", env.uint32(id as u32)?)?;
+
+    exports.set_named_property(
+        "calls",
+        env.func(|this, ()| {
+            let calls = this.env().state_or_init(|| Ok(Calls(Cell::new(0))))?;
+            calls.0.set(calls.0.get() + 1);
+            Ok(calls.0.get())
+        })?,
+    )?;
+
+    exports.set_named_property(
+        "loaded",
+        env.func(|_, ()| Ok(ModuleContext::loaded() as u32))?,
+    )?;
+
+    exports.set_named_property(
+        "leak",
+        env.func(|this, value: JsValue| {
+            *LEAKED.lock().unwrap() = Some(Leaked(value));
+            this.env().undefined()
+        })?,
+    )?;
+
+    exports.set_named_property(
+        "keep",
+        env.func(|this, value: JsObject| {
+            KEPT.lock().unwrap().push(Persistent::new(value)?);
+            this.env().undefined()
+        })?,
+    )?;
+
+    exports.set_named_property(
+        "release",
+        env.func(|_, ()| {
+            let kept = std::mem::take(&mut *KEPT.lock().unwrap());
+            Ok(kept.len() as u32)
+        })?,
+    )?;
+
+    exports.set_named_property(
+        "sleep",
+        env.func(|this, ms: u32| {
+            // NB: the future may complete after the env is torn down
+            this.env().spawn_future(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(ms as u64)).await;
+                ms
+            })
+        })?,
+    )?;
+
+    exports.set_named_property(
+        "leaked",
+        env.func(|this, ()| {
+            let leaked = *LEAKED.lock().unwrap();
+            match leaked {
+                // NB: it panics in debug builds if the value is leaked from another env
+                Some(Leaked(value)) => Ok(value),
+                None => Ok(this.env().undefined()?.value()),
+            }
+        })?,
+    )?;
+
+    Ok(())
+}
@@ -1,5 +1,21 @@
 #[doc = include_str!("../README.md")]
 use nodex::prelude::*;
+use nodex::{module::ModuleContext, plugin::NapiPlugin};
+
+/// The plugin installed on `exports.helloworld` of the addon depending on this crate.
+pub struct HelloWorld;
+
+impl NapiPlugin for HelloWorld {
+    fn name(&self) -> &'static str {
+        "helloworld"
+    }
+
+    fn init(&self, context: &ModuleContext, exports: JsObject) -> NapiResult<()> {
+        init(context.env(), exports)
+    }
+}
+
+nodex::napi_plugin!(HelloWorld);
 
 pub fn init(env: NapiEnv, mut object: JsObject) -> NapiResult<()> {
     object.set_named_property(
@@ -1,176 +1,113 @@
 use either::Either;
 use nodex::prelude::*;
+use serde::Deserialize;
 use sled::*;
-use std::sync::Arc;
-
-type SledInner = Arc<Either<Db, Tree>>;
-
-pub fn class(env: NapiEnv) -> NapiResult<JsClass> {
-    JsClass::new(
-        env,
-        "sled",
-        move |mut this, params: JsObject| {
-            let env = this.env();
-            let desc = [
-                DescriptorMethodBuilder::new()
-                    .with_utf8name("get")
-                    .with_method(move |this, key: JsArrayBuffer| {
-                        let env = this.env();
-                        let inner = if let Some(inner) = this.unwrap::<SledInner>()? {
-                            inner
-                        } else {
-                            env.throw_error("sled context missing")?;
-                            return env.undefined().map(|u| u.value());
-                        };
-
-                        let key = key.buffer()?;
-                        match &**inner {
-                            Either::Left(ref db) => {
-                                if let Some(value) = db.get(key).unwrap() {
-                                    env.arraybuffer(value).map(|buffer| buffer.value())
-                                } else {
-                                    env.null().map(|u| u.value())
-                                }
-                            }
-                            Either::Right(ref tree) => {
-                                if let Some(value) = tree.get(key).unwrap() {
-                                    env.arraybuffer(value).map(|buffer| buffer.value())
-                                } else {
-                                    env.null().map(|u| u.value())
-                                }
-                            }
-                        }
-                    })
-                    .build()?,
-                DescriptorMethodBuilder::new()
-                    .with_utf8name("insert")
-                    .with_method(move |this, (key, value): (JsArrayBuffer, JsArrayBuffer)| {
-                        let env = this.env();
-                        let inner = if let Some(inner) = this.unwrap::<SledInner>()? {
-                            inner
-                        } else {
-                            env.throw_error("sled context missing")?;
-                            return env.undefined().map(|u| u.value());
-                        };
-
-                        match &**inner {
-                            Either::Left(ref db) => {
-                                if let Some(value) =
-                                    db.insert(key.buffer()?, value.buffer()?).unwrap()
-                                {
-                                    env.arraybuffer(value).map(|buffer| buffer.value())
-                                } else {
-                                    env.null().map(|u| u.value())
-                                }
-                            }
-                            Either::Right(ref tree) => {
-                                if let Some(value) =
-                                    tree.insert(key.buffer()?, value.buffer()?).unwrap()
-                                {
-                                    env.arraybuffer(value).map(|buffer| buffer.value())
-                                } else {
-                                    env.null().map(|u| u.value())
-                                }
-                            }
-                        }
-                    })
-                    .build()?,
-                DescriptorMethodBuilder::new()
-                    .with_utf8name("remove")
-                    .with_method(move |this, key: JsArrayBuffer| {
-                        let env = this.env();
-                        let inner = if let Some(inner) = this.unwrap::<SledInner>()? {
-                            inner
-                        } else {
-                            env.throw_error("sled context missing")?;
-                            return env.undefined().map(|u| u.value());
-                        };
-
-                        let key = key.buffer()?;
-                        match &**inner {
-                            Either::Left(ref db) => {
-                                if let Some(value) = db.remove(key).unwrap() {
-                                    env.arraybuffer(value).map(|buffer| buffer.value())
-                                } else {
-                                    env.null().map(|u| u.value())
-                                }
-                            }
-                            Either::Right(ref tree) => {
-                                if let Some(value) = tree.remove(key).unwrap() {
-                                    env.arraybuffer(value).map(|buffer| buffer.value())
-                                } else {
-                                    env.null().map(|u| u.value())
-                                }
-                            }
-                        }
-                    })
-                    .build()?,
-            ];
-
-            let path: JsValue = params.get("path")?;
-            let use_compression: JsValue = params.get("use_compression")?;
-
-            let mut config = sled::Config::new();
-
-            if let Ok(path) = path.cast_checked::<JsString>() {
-                config = config.path(path.get()?);
-            }
 
-            if let Ok(use_compression) = use_compression.cast_checked::<JsBoolean>() {
-                config = config.use_compression(use_compression.get()?);
-            }
+/// the params of `new sled(params)`
+#[derive(Deserialize)]
+struct Params {
+    path: Option<String>,
+    use_compression: Option<bool>,
+    cache_capacity: Option<u64>,
+}
+
+/// a sled db, or a tree opened from it
+#[nodex::class(name = "sled")]
+pub struct Sled(Either<Db, Tree>);
+
+impl Sled {
+    fn tree(&self) -> &Tree {
+        match &self.0 {
+            Either::Left(db) => db,
+            Either::Right(tree) => tree,
+        }
+    }
+
+    fn db(&self, env: NapiEnv) -> NapiResult<&Db> {
+        match &self.0 {
+            Either::Left(db) => Ok(db),
+            Either::Right(_) => throw(env, "sled: not a db"),
+        }
+    }
+}
 
-            let db = match config.open() {
-                Ok(db) => db,
-                Err(e) => {
-                    env.throw_error(format!("{}", e))?;
-                    return env.undefined();
-                }
-            };
-
-            this.define_properties(&desc)?;
-
-            let db1 = db.clone();
-            this.set(
-                "open_tree",
-                env.func(move |this, tree_name: JsString| {
-                    let env = this.env();
-                    let name = tree_name.get()?;
-                    let mut tree_obj = env.object()?;
-
-                    let tree = db1.open_tree(name).unwrap();
-
-                    tree_obj.wrap(
-                        Arc::new(Either::<Db, Tree>::Right(tree)),
-                        move |_, _: SledInner| Ok(()),
-                    )?;
-
-                    tree_obj.define_properties(&desc)?;
-
-                    Ok(tree_obj)
-                })?,
-            )?;
-
-            let db1 = db.clone();
-            this.set(
-                "tree_names",
-                env.func(move |this, ()| {
-                    let env = this.env();
-                    let mut array = env.array()?;
-
-                    for (idx, name) in db1.tree_names().into_iter().enumerate() {
-                        let name = env.arraybuffer(&name)?;
-                        array.set(idx as u32, name)?;
-                    }
-
-                    Ok(array)
-                })?,
-            )?;
-
-            this.wrap(Arc::new(Either::<Db, Tree>::Left(db)), move |_, _| Ok(()))?;
-
-            this.undefined()
-        },
-        &[],
-    )
+/// the value is handed to js as an arraybuffer without copying, or null if missing
+fn value(env: NapiEnv, value: sled::Result<Option<IVec>>) -> NapiResult<JsValue> {
+    match value {
+        Ok(Some(value)) => JsArrayBuffer::from_external(env, value).map(|buffer| buffer.value()),
+        Ok(None) => env.null().map(|null| null.value()),
+        Err(e) => throw(env, e),
+    }
+}
+
+fn throw<T>(env: NapiEnv, e: impl std::fmt::Display) -> NapiResult<T> {
+    env.throw_error(format!("{}", e))?;
+    Err(NapiStatus::PendingException)
+}
+
+#[nodex::methods]
+impl Sled {
+    #[constructor]
+    fn open(env: NapiEnv, params: JsValue) -> NapiResult<Self> {
+        let params: Params = match nodex::serde::from_js(params) {
+            Ok(params) => params,
+            Err(e) => {
+                env.throw_type_error(format!("sled: {}", e))?;
+                return Err(NapiStatus::PendingException);
+            }
+        };
+
+        let mut config = sled::Config::new();
+
+        if let Some(path) = params.path {
+            config = config.path(path);
+        }
+
+        if let Some(use_compression) = params.use_compression {
+            config = config.use_compression(use_compression);
+        }
+
+        if let Some(cache_capacity) = params.cache_capacity {
+            config = config.cache_capacity(cache_capacity);
+        }
+
+        match config.open() {
+            Ok(db) => Ok(Sled(Either::Left(db))),
+            Err(e) => throw(env, e),
+        }
+    }
+
+    fn get(&self, env: NapiEnv, key: JsArrayBuffer) -> NapiResult<JsValue> {
+        value(env, self.tree().get(key.buffer()?))
+    }
+
+    fn insert(
+        &self,
+        env: NapiEnv,
+        key: JsArrayBuffer,
+        value: JsArrayBuffer,
+    ) -> NapiResult<JsValue> {
+        self::value(env, self.tree().insert(key.buffer()?, value.buffer()?))
+    }
+
+    fn remove(&self, env: NapiEnv, key: JsArrayBuffer) -> NapiResult<JsValue> {
+        value(env, self.tree().remove(key.buffer()?))
+    }
+
+    fn open_tree(&self, env: NapiEnv, name: String) -> NapiResult<Self> {
+        match self.db(env)?.open_tree(name) {
+            Ok(tree) => Ok(Sled(Either::Right(tree))),
+            Err(e) => throw(env, e),
+        }
+    }
+
+    fn tree_names(&self, env: NapiEnv) -> NapiResult<JsArray> {
+        let mut array = env.array()?;
+
+        for (idx, name) in self.db(env)?.tree_names().into_iter().enumerate() {
+            array.set(idx as u32, JsArrayBuffer::from_external(env, name)?)?;
+        }
+
+        Ok(array)
+    }
 }
@@ -4,7 +4,6 @@ nodex::napi_module!(init);
 
 mod db;
 
-fn init(env: NapiEnv, mut exports: JsObject) -> NapiResult<()> {
-    exports.set("sled", db::class(env)?)?;
+fn init(_env: NapiEnv, _exports: JsObject) -> NapiResult<()> {
     Ok(())
 }